@@ -1,18 +1,18 @@
 use std::{
     fs::{self, File},
-    path::PathBuf,
-    process,
+    path::{Path, PathBuf},
 };
 
 use anyhow::Result;
 use clap::{Args, Parser};
-use colored::Colorize;
 use serde::{Deserialize, Serialize};
 
 use crate::{
     commands::MukdukCommands,
-    config::{ConfigEnvKey, MukdukConfig},
+    config::{ConfigEnvKey, ConfigFormat, MukdukConfig},
+    error::MukdukError,
     fzf::FzfCmd,
+    multiplexer::tmux::TmuxSocket,
 };
 
 #[derive(Parser)]
@@ -34,7 +34,8 @@ struct SharedArgs {
     #[arg(long, env)]
     projects_dir: Option<PathBuf>,
 
-    /// Override '$XDG_CONFIG_HOME/config.yml' or '$HOME/.mukdukrc.yml' defaults.
+    /// Override '$XDG_CONFIG_HOME/mukduk/config.{toml,yaml,yml,json}' or
+    /// '$HOME/.mukdukrc.{toml,yaml,yml,json}' defaults.
     #[arg(short, long)]
     config_path: Option<PathBuf>,
 
@@ -42,14 +43,70 @@ struct SharedArgs {
     #[arg(short, long)]
     pick_projects_dir: bool,
 
+    /// tmux server to use, a socket name (`tmux -L`) or a path to a socket (`tmux -S`).
+    /// Overrides `tmux.socket_name` and `tmux.socket_path` from the config file.
+    #[arg(long, env = "MUKDUK_TMUX_SOCKET")]
+    tmux_socket: Option<TmuxSocket>,
+
+    /// Show pickers in a tmux popup instead of the current pane, when run inside of tmux.
+    /// Same as `picker.popup` in the config file.
+    #[arg(long)]
+    popup: bool,
+
     #[clap(flatten)]
     verbosity: clap_verbosity_flag::Verbosity,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
-struct MukdukContext {
-    config_path: PathBuf,
-    config: MukdukConfig,
+pub struct MukdukContext {
+    pub config_path: PathBuf,
+    pub config: MukdukConfig,
+    pub tmux_socket: Option<TmuxSocket>,
+    projects_dir: Option<PathBuf>,
+    pick_projects_dir: bool,
+}
+
+impl MukdukContext {
+    /// Resolve the projects dir from the cli args or config file, letting the user pick one of
+    /// the configured options when requested.
+    pub fn projects_dir(&self) -> Result<PathBuf> {
+        let mut projects_dir = self
+            .projects_dir
+            .clone()
+            .or_else(|| self.config.projects_dir.default.clone())
+            .ok_or(MukdukError::MissingConfigKey("projects_dir"))?;
+        if self.pick_projects_dir {
+            log::trace!("user picking project dir...");
+            if let Some(dirs) = &self.config.projects_dir.options {
+                let string_dir_names: Vec<String> = dirs
+                    .iter()
+                    .map(|d| d.to_string_lossy().to_string())
+                    .collect();
+                let selected = PathBuf::from(
+                    FzfCmd::new()
+                        .picker(&self.config.picker)
+                        .find_vec(string_dir_names)?,
+                );
+                log::trace!(
+                    "expanding project dir selection: [{}]",
+                    selected.to_string_lossy()
+                );
+                match std::fs::canonicalize(selected) {
+                    Ok(curr) => {
+                        log::trace!("user picked [{}] as project dir.", curr.to_string_lossy());
+                        projects_dir = curr
+                    }
+                    Err(err) => {
+                        log::trace!(
+                            "failed expanding project dir selection. using default of [{}]: {err}",
+                            projects_dir.to_string_lossy()
+                        );
+                    }
+                }
+            }
+        }
+        Ok(projects_dir)
+    }
 }
 
 impl MukdukCli {
@@ -62,6 +119,13 @@ impl MukdukCli {
 
         cli.set_config_path()?;
         cli.context.config = MukdukConfig::from_file(&cli.context.config_path)?;
+        cli.context.projects_dir = cli.args.projects_dir.clone();
+        cli.context.pick_projects_dir = cli.args.pick_projects_dir;
+        cli.context.config.picker.popup |= cli.args.popup;
+        cli.context.tmux_socket = match cli.args.tmux_socket.clone() {
+            Some(socket) => Some(socket),
+            None => cli.context.config.tmux.socket()?,
+        };
 
         log::debug!("{:#?}", &cli.args);
         log::debug!("{:#?}", &cli.context);
@@ -72,18 +136,11 @@ impl MukdukCli {
 
     pub fn set_config_path(&mut self) -> Result<()> {
         if let Some(config_path) = &self.args.config_path {
-            if let Ok(curr) = std::fs::canonicalize(config_path) {
-                log::debug!("checking {}", curr.to_string_lossy());
-                if !curr.exists() {
-                    eprintln!(
-                        "\n{}\n",
-                        "Provided config path does not exist.".red().bold()
-                    );
-                    process::exit(1);
-                }
-                self.args.config_path = Some(curr.clone());
-                self.context.config_path = curr;
-            }
+            log::debug!("checking {}", config_path.to_string_lossy());
+            let curr = std::fs::canonicalize(config_path)
+                .map_err(|_| MukdukError::ConfigNotFound(config_path.clone()))?;
+            self.args.config_path = Some(curr.clone());
+            self.context.config_path = curr;
         } else {
             let mut path = PathBuf::try_from(ConfigEnvKey::XDGConfig)?;
             if path.exists() {
@@ -91,17 +148,11 @@ impl MukdukCli {
                 if !path.exists() {
                     fs::create_dir(&path)?;
                 }
-                path.push("config.toml");
-                if !path.exists() {
-                    File::create(&path)?;
-                }
+                path = Self::find_or_create_config(&path, "config")?;
             } else {
-                let mut path = PathBuf::try_from(ConfigEnvKey::Home)?;
+                path = PathBuf::try_from(ConfigEnvKey::Home)?;
                 if path.exists() {
-                    path.push(".mukdukrc.toml");
-                    if !path.exists() {
-                        File::create(&path)?;
-                    }
+                    path = Self::find_or_create_config(&path, ".mukdukrc")?;
                 }
             }
             self.args.config_path = Some(path.clone());
@@ -110,50 +161,18 @@ impl MukdukCli {
         Ok(())
     }
 
-    pub fn handle_cmd(self) -> Result<()> {
-        if let Some(cmd) = self.command {
-            let mut projects_dir = self
-                .args
-                .projects_dir
-                .or(self.context.config.projects_dir.default)
-                .expect("should be set");
-            if self.args.pick_projects_dir {
-                log::trace!("user picking project dir...");
-                if let Some(dirs) = self.context.config.projects_dir.options {
-                    let string_dir_names: Vec<String> = dirs
-                        .iter()
-                        .map(|d| d.to_string_lossy().to_string())
-                        .collect();
-                    let selected = PathBuf::from(FzfCmd::new().find_vec(string_dir_names)?);
-                    log::trace!(
-                        "expanding project dir selection: [{}]",
-                        selected.to_string_lossy()
-                    );
-                    match std::fs::canonicalize(selected) {
-                        Ok(curr) => {
-                            log::trace!(
-                                "user picked [{}] as project dir.",
-                                projects_dir.to_string_lossy()
-                            );
-                            projects_dir = curr
-                        }
-                        Err(err) => {
-                            log::trace!("failed expanding project dir selection. using default of [{}]: {err}", projects_dir.to_string_lossy());
-                        }
-                    }
-                }
-            }
-            MukdukCommands::handle_cmd(cmd, projects_dir)?;
-        } else {
-            eprintln!(
-                "\n{}\n",
-                "No command was provided! To see commands use `--help`."
-                    .yellow()
-                    .bold()
-            );
-            std::process::exit(1);
+    /// Use the single existing `{stem}.*` config in `dir`, or create an empty toml one.
+    fn find_or_create_config(dir: &Path, stem: &str) -> Result<PathBuf> {
+        if let Some(existing) = MukdukConfig::find_in(dir, stem)? {
+            return Ok(existing);
         }
+        let path = dir.join(format!("{stem}.{}", ConfigFormat::Toml.extension()));
+        File::create(&path)?;
+        Ok(path)
+    }
 
-        Ok(())
+    pub fn handle_cmd(self) -> Result<()> {
+        let cmd = self.command.ok_or(MukdukError::NoCommand)?;
+        MukdukCommands::handle_cmd(cmd, &self.context)
     }
 }
@@ -0,0 +1,139 @@
+use anyhow::Result;
+use clap::{Args, ValueEnum};
+use clap_complete::{env::Shells, shells::Shell, CompletionCandidate};
+use std::{collections::BTreeSet, env, io, path::PathBuf};
+
+use crate::{
+    config::MukdukConfig,
+    helper::{dir_name, get_directories, get_projects},
+    multiplexer::{BackendOptions, Multiplexers},
+    project::Project,
+    sources::ProjectSource,
+};
+
+/// Env var the shell sets when asking mukduk for completions.
+pub const COMPLETE_VAR: &str = "COMPLETE";
+
+#[derive(Args, Debug)]
+pub struct CompletionsArgs {
+    #[arg(value_enum)]
+    /// Shell to print the completion script for.
+    pub shell: Shell,
+}
+
+impl CompletionsArgs {
+    pub fn handle_cmd(completions_args: Self) -> Result<()> {
+        let shell = completions_args.shell.to_string();
+        let shells = Shells::builtins();
+        let completer = shells
+            .completer(&shell)
+            .ok_or_else(|| anyhow::anyhow!("completions are not supported for {shell}"))?;
+        completer.write_registration(
+            COMPLETE_VAR,
+            "mukduk",
+            "mukduk",
+            "mukduk",
+            &mut io::stdout(),
+        )?;
+        Ok(())
+    }
+}
+
+/// Projects in the default projects dir. Completion has no access to the parsed cli args, so
+/// only `$PROJECTS_DIR` and the default config file are considered. Command sources are skipped,
+/// since they can be slow and completion runs on every tab.
+fn projects() -> Vec<Project> {
+    let mut config = MukdukConfig::load_default().unwrap_or_default();
+    config
+        .sources
+        .retain(|source| !matches!(source, ProjectSource::Command { .. }));
+    env::var_os("PROJECTS_DIR")
+        .map(PathBuf::from)
+        .or_else(|| config.projects_dir.default.clone())
+        .and_then(|projects_dir| get_projects(&projects_dir, &config, &[]).ok())
+        .unwrap_or_default()
+}
+
+pub fn complete_project_names() -> Vec<CompletionCandidate> {
+    projects()
+        .into_iter()
+        .map(|project| {
+            CompletionCandidate::new(project.get_name()).help(Some(
+                project.get_path().to_string_lossy().to_string().into(),
+            ))
+        })
+        .collect()
+}
+
+pub fn complete_project_dirs() -> Vec<CompletionCandidate> {
+    projects()
+        .into_iter()
+        .map(|project| {
+            CompletionCandidate::new(project.get_path()).help(Some(project.get_name().into()))
+        })
+        .collect()
+}
+
+/// Projects in `archive_dir` that can be restored.
+pub fn complete_archived() -> Vec<CompletionCandidate> {
+    MukdukConfig::load_default()
+        .ok()
+        .and_then(|config| config.archive_dir)
+        .and_then(|archive_dir| get_directories(&archive_dir).ok())
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|dir| dir_name(dir).ok())
+        .map(CompletionCandidate::new)
+        .collect()
+}
+
+/// Templates configured for `project create`.
+pub fn complete_templates() -> Vec<CompletionCandidate> {
+    MukdukConfig::load_default()
+        .unwrap_or_default()
+        .templates
+        .into_iter()
+        .map(|(name, dir)| {
+            CompletionCandidate::new(name).help(Some(dir.to_string_lossy().to_string().into()))
+        })
+        .collect()
+}
+
+/// Every tag used by at least one project.
+pub fn complete_tags() -> Vec<CompletionCandidate> {
+    let tags: BTreeSet<String> = projects()
+        .into_iter()
+        .flat_map(|project| project.tags)
+        .collect();
+    tags.into_iter().map(CompletionCandidate::new).collect()
+}
+
+/// Live sessions from every multiplexer that is installed and running.
+pub fn complete_sessions() -> Vec<CompletionCandidate> {
+    let tmux_socket = env::var("MUKDUK_TMUX_SOCKET").map_or_else(
+        |_| {
+            MukdukConfig::load_default()
+                .ok()
+                .and_then(|config| config.tmux.socket().ok().flatten())
+        },
+        |socket| socket.parse().ok(),
+    );
+
+    Multiplexers::value_variants()
+        .iter()
+        .flat_map(|multiplexer| {
+            let backend = multiplexer.backend(BackendOptions {
+                tmux_socket: tmux_socket.clone(),
+                ..Default::default()
+            });
+            let help = format!("{multiplexer:?} session").to_lowercase();
+            backend
+                .get_sessions()
+                .unwrap_or_default()
+                .into_iter()
+                .map(move |session| {
+                    CompletionCandidate::new(session).help(Some(help.clone().into()))
+                })
+        })
+        .collect()
+}
@@ -0,0 +1,61 @@
+use clap::Subcommand;
+use colored::Colorize;
+use std::{fs, path::PathBuf};
+
+use crate::{cli::MukdukContext, config::ConfigFormat, error::MukdukError};
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigSubcommand {
+    /// Rewrite the current config file in another format.
+    ///
+    /// Unless `--output` is given the new file is written next to the current one, and the
+    /// current one is renamed with a `.bak` suffix so only one config file is left behind.
+    Convert {
+        #[arg(short, long, value_enum)]
+        /// Format to convert the config file to.
+        to: ConfigFormat,
+
+        #[arg(short, long)]
+        /// Where to write the converted config file.
+        output: Option<PathBuf>,
+
+        #[arg(short, long)]
+        /// Overwrite the output file if it already exists.
+        force: bool,
+    },
+}
+
+impl ConfigSubcommand {
+    pub fn handle_cmd(config_sub_cmd: Self, context: &MukdukContext) -> anyhow::Result<()> {
+        match config_sub_cmd {
+            Self::Convert { to, output, force } => {
+                let config_path = &context.config_path;
+                let from = ConfigFormat::from_path(config_path)?;
+                let in_place = output.is_none();
+                let output = output.unwrap_or_else(|| config_path.with_extension(to.extension()));
+
+                // Converting in place to the same format would overwrite the only copy.
+                if output == *config_path || (output.exists() && !force) {
+                    return Err(MukdukError::AlreadyExists(output).into());
+                }
+                let converted = to.serialize(&from.parse(&fs::read_to_string(config_path)?)?)?;
+
+                log::debug!("writing converted config to {output:?}...");
+                fs::write(&output, converted)?;
+
+                if in_place {
+                    let mut backup = config_path.clone().into_os_string();
+                    backup.push(".bak");
+                    log::debug!("moving {config_path:?} to {backup:?}...");
+                    fs::rename(config_path, &backup)?;
+                }
+
+                eprintln!(
+                    "{}",
+                    format!("Wrote {}", output.to_string_lossy()).green().bold()
+                );
+                Ok(())
+            }
+        }
+    }
+}
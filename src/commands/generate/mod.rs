@@ -0,0 +1,181 @@
+use anyhow::Result;
+use clap::{Arg, Args, Command, CommandFactory, ValueEnum};
+use std::{fmt::Write, fs, path::PathBuf};
+
+use crate::cli::MukdukCli;
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+pub enum DocFormat {
+    /// One roff man page per command (`mukduk-project-open.1`).
+    Man,
+    /// A single markdown reference covering every command (`mukduk.md`).
+    Markdown,
+}
+
+#[derive(Args, Debug)]
+pub struct GenerateArgs {
+    #[arg(value_enum)]
+    /// Kind of docs to generate.
+    pub format: DocFormat,
+
+    /// Directory to write the docs into, created if it does not exist.
+    pub out_dir: PathBuf,
+}
+
+impl GenerateArgs {
+    pub fn handle_cmd(generate_args: Self) -> Result<()> {
+        let cmd = reference_command();
+        fs::create_dir_all(&generate_args.out_dir)?;
+
+        match generate_args.format {
+            DocFormat::Man => clap_mangen::generate_to(cmd, &generate_args.out_dir)?,
+            DocFormat::Markdown => {
+                let path = generate_args.out_dir.join("mukduk.md");
+                fs::write(&path, render_markdown(&cmd))?;
+            }
+        }
+        log::info!("wrote docs to {}", generate_args.out_dir.to_string_lossy());
+        Ok(())
+    }
+}
+
+/// The full command tree without clap's generated `help` subcommands, which would otherwise
+/// each get their own page.
+pub fn reference_command() -> Command {
+    fn without_help(cmd: Command) -> Command {
+        cmd.disable_help_subcommand(true)
+            .mut_subcommands(without_help)
+    }
+    let mut cmd = without_help(MukdukCli::command());
+    cmd.build();
+    cmd
+}
+
+/// Render every visible command in the tree, depth first, as one markdown document.
+pub fn render_markdown(cmd: &Command) -> String {
+    let mut out = format!("# Command-Line Help for `{}`\n", cmd.get_name());
+    render_command(cmd, &mut out);
+    out
+}
+
+fn bin_name(cmd: &Command) -> &str {
+    cmd.get_bin_name().unwrap_or_else(|| cmd.get_name())
+}
+
+fn render_command(cmd: &Command, out: &mut String) {
+    let name = bin_name(cmd);
+    let _ = write!(out, "\n## `{name}`\n\n");
+    if let Some(about) = cmd.get_long_about().or_else(|| cmd.get_about()) {
+        let _ = write!(out, "{about}\n\n");
+    }
+    let usage = cmd.clone().render_usage().to_string();
+    let usage = usage.trim_start_matches("Usage: ");
+    let _ = writeln!(out, "**Usage:** `{usage}`");
+
+    let subcommands: Vec<_> = cmd.get_subcommands().filter(|s| !s.is_hide_set()).collect();
+    if !subcommands.is_empty() {
+        out.push_str("\n###### **Subcommands:**\n\n");
+        for sub in &subcommands {
+            let about = sub.get_about().map(|a| a.to_string()).unwrap_or_default();
+            let _ = writeln!(out, "* `{}` — {about}", sub.get_name());
+        }
+    }
+
+    let (positionals, options): (Vec<&Arg>, Vec<&Arg>) = cmd
+        .get_arguments()
+        .filter(|arg| !arg.is_hide_set())
+        .partition(|arg| arg.is_positional());
+    for (title, args) in [("Arguments", positionals), ("Options", options)] {
+        if args.is_empty() {
+            continue;
+        }
+        let _ = write!(out, "\n###### **{title}:**\n\n");
+        for arg in args {
+            render_arg(arg, out);
+        }
+    }
+
+    for sub in subcommands {
+        render_command(sub, out);
+    }
+}
+
+fn render_arg(arg: &Arg, out: &mut String) {
+    let value_names = arg
+        .get_value_names()
+        .map(|names| {
+            names
+                .iter()
+                .map(|n| format!("<{n}>"))
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .unwrap_or_else(|| format!("<{}>", arg.get_id().as_str().to_uppercase()));
+
+    let signature = if arg.is_positional() {
+        value_names
+    } else {
+        let mut flags = Vec::new();
+        if let Some(short) = arg.get_short() {
+            flags.push(format!("-{short}"));
+        }
+        if let Some(long) = arg.get_long() {
+            flags.push(format!("--{long}"));
+        }
+        let mut signature = flags.join(", ");
+        if arg.get_action().takes_values() {
+            signature.push(' ');
+            signature.push_str(&value_names);
+        }
+        signature
+    };
+
+    let help = arg
+        .get_long_help()
+        .or_else(|| arg.get_help())
+        .map(|h| h.to_string().replace('\n', " "))
+        .unwrap_or_default();
+    if help.is_empty() {
+        let _ = writeln!(out, "* `{signature}`");
+    } else {
+        let _ = writeln!(out, "* `{signature}` — {help}");
+    }
+
+    if let Some(env) = arg.get_env() {
+        let _ = writeln!(out, "  * env: `{}`", env.to_string_lossy());
+    }
+    let defaults: Vec<_> = arg
+        .get_default_values()
+        .iter()
+        .map(|d| format!("`{}`", d.to_string_lossy()))
+        .collect();
+    if !defaults.is_empty() && arg.get_action().takes_values() {
+        let _ = writeln!(out, "  * default: {}", defaults.join(", "));
+    }
+    let possible: Vec<_> = arg
+        .get_possible_values()
+        .into_iter()
+        .filter(|pv| !pv.is_hide_set())
+        .map(|pv| format!("`{}`", pv.get_name()))
+        .collect();
+    if !possible.is_empty() && arg.get_action().takes_values() {
+        let _ = writeln!(out, "  * possible values: {}", possible.join(", "));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{reference_command, render_markdown};
+
+    #[test]
+    fn should_render_every_visible_command_with_env_vars() {
+        let markdown = render_markdown(&reference_command());
+
+        assert!(markdown.contains("## `mukduk project open`"));
+        assert!(markdown.contains("## `mukduk config convert`"));
+        assert!(markdown.contains("* env: `PROJECTS_DIR`"));
+        assert!(markdown.contains("* possible values: `tmux`, `zellij`"));
+        assert!(!markdown.contains("## `mukduk generate`"));
+        assert!(!markdown.contains("## `mukduk project help`"));
+    }
+}
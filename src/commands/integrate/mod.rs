@@ -0,0 +1,152 @@
+use anyhow::Result;
+use clap::Subcommand;
+use colored::Colorize;
+use std::{fs, path::PathBuf};
+
+use crate::config::ConfigEnvKey;
+
+/// Marks the lines `integrate tmux --write` owns, so writing again replaces them.
+const BLOCK_START: &str = "# >>> mukduk >>>";
+const BLOCK_END: &str = "# <<< mukduk <<<";
+
+/// Key after the tmux prefix, zellij key, what it does, and the `mukduk` args it runs. Zellij
+/// already uses most `Alt` keys, so the two do not always match.
+const BINDINGS: [(&str, &str, &str, &str); 4] = [
+    ("f", "Alt p", "pick a project to open", "project open"),
+    ("X", "Alt x", "pick sessions to kill", "project kill"),
+    ("H", "Alt e", "open a new home session", "project home"),
+    ("S", "Alt s", "open the scratch session", "project scratch"),
+];
+
+#[derive(Subcommand, Debug)]
+pub enum IntegrateSubcommand {
+    /// Print tmux key bindings for the project picker, kill, home, and scratch.
+    ///
+    /// Every binding runs mukduk in a popup under the prefix key, like `prefix f` for the picker.
+    Tmux {
+        #[arg(short, long)]
+        /// Add the bindings to `~/.tmux.conf` instead of printing them, replacing ones written
+        /// before.
+        write: bool,
+    },
+    /// Print a zellij keybinds snippet for the project picker, kill, home, and scratch.
+    ///
+    /// Zellij can not switch sessions from the command line, so sessions are created in the
+    /// background and switched to with zellij's session manager.
+    Zellij,
+}
+
+impl IntegrateSubcommand {
+    pub fn handle_cmd(integrate_sub_cmd: Self) -> Result<()> {
+        match integrate_sub_cmd {
+            Self::Tmux { write: false } => {
+                print!("{}", tmux_bindings());
+                Ok(())
+            }
+            Self::Tmux { write: true } => {
+                let tmux_conf = PathBuf::try_from(ConfigEnvKey::Home)?.join(".tmux.conf");
+                let current = match fs::read_to_string(&tmux_conf) {
+                    Ok(current) => current,
+                    Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+                    Err(err) => return Err(err.into()),
+                };
+                log::debug!("writing key bindings to {tmux_conf:?}...");
+                fs::write(&tmux_conf, replace_block(&current, &tmux_bindings()))?;
+                eprintln!(
+                    "{}",
+                    format!("Wrote {}", tmux_conf.to_string_lossy())
+                        .green()
+                        .bold()
+                );
+                eprintln!("Reload it with `tmux source-file ~/.tmux.conf`.");
+                Ok(())
+            }
+            Self::Zellij => {
+                print!("{}", zellij_keybinds());
+                Ok(())
+            }
+        }
+    }
+}
+
+fn tmux_bindings() -> String {
+    let mut bindings = format!("{BLOCK_START}\n");
+    for (key, _, description, args) in BINDINGS {
+        bindings.push_str(&format!(
+            "# prefix {key}: {description}\nbind-key {key} display-popup -E -w 80% -h 60% \"mukduk {args} -m tmux\"\n"
+        ));
+    }
+    bindings.push_str(BLOCK_END);
+    bindings.push('\n');
+    bindings
+}
+
+fn zellij_keybinds() -> String {
+    let mut keybinds = String::from(
+        "// Add to the keybinds section of your zellij config, then switch to opened sessions\n\
+         // with the session manager (Ctrl o, w).\n\
+         keybinds {\n    shared_except \"locked\" {\n",
+    );
+    for (_, key, description, args) in BINDINGS {
+        // Kill has no use for `-d`, it never opens anything.
+        let detach = (args != "project kill").then_some("-d");
+        let args: String = args
+            .split_whitespace()
+            .chain(["-m", "zellij"])
+            .chain(detach)
+            .map(|arg| format!(" \"{arg}\""))
+            .collect();
+        keybinds.push_str(&format!(
+            "        // {key}: {description}\n        bind \"{key}\" {{\n            Run \"mukduk\"{args} {{\n                floating true\n                close_on_exit true\n            }}\n            SwitchToMode \"Normal\"\n        }}\n"
+        ));
+    }
+    keybinds.push_str("    }\n}\n");
+    keybinds
+}
+
+/// Replace the mukduk block in `conf` with `block`, or append `block` when there is none.
+fn replace_block(conf: &str, block: &str) -> String {
+    if let (Some(start), Some(end)) = (conf.find(BLOCK_START), conf.find(BLOCK_END)) {
+        if start < end {
+            let after = &conf[end + BLOCK_END.len()..];
+            let after = after.strip_prefix('\n').unwrap_or(after);
+            return format!("{}{block}{after}", &conf[..start]);
+        }
+    }
+    match conf {
+        "" => block.to_string(),
+        conf if conf.ends_with('\n') => format!("{conf}\n{block}"),
+        conf => format!("{conf}\n\n{block}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::{replace_block, tmux_bindings, zellij_keybinds};
+
+    #[test]
+    fn should_append_then_replace_block() {
+        let block = tmux_bindings();
+        let conf = replace_block("set -g mouse on\n", &block);
+        assert_eq!(conf, format!("set -g mouse on\n\n{block}"));
+
+        let conf = format!("{conf}set -g base-index 1\n");
+        let rewritten = replace_block(&conf, &block);
+        assert_eq!(rewritten, conf);
+        assert_eq!(rewritten.matches("bind-key f").count(), 1);
+    }
+
+    #[test]
+    fn should_bind_every_command() {
+        let bindings = tmux_bindings();
+        assert!(bindings
+            .contains("bind-key f display-popup -E -w 80% -h 60% \"mukduk project open -m tmux\""));
+        assert_eq!(bindings.matches("bind-key").count(), 4);
+
+        let keybinds = zellij_keybinds();
+        assert!(keybinds.contains("Run \"mukduk\" \"project\" \"open\" \"-m\" \"zellij\" \"-d\""));
+        assert!(keybinds.contains("Run \"mukduk\" \"project\" \"kill\" \"-m\" \"zellij\" {"));
+    }
+}
@@ -1,22 +1,65 @@
 use clap::Subcommand;
-use std::path::PathBuf;
 
-use self::project::ProjectSubcommand;
+use crate::cli::MukdukContext;
+
+use self::{
+    completions::CompletionsArgs, config::ConfigSubcommand, generate::GenerateArgs,
+    integrate::IntegrateSubcommand, project::ProjectSubcommand, session::SessionSubcommand,
+    switch::SwitchArgs,
+};
+pub mod completions;
+pub mod config;
+pub mod generate;
+pub mod integrate;
 pub mod project;
+pub mod session;
+pub mod switch;
 
 #[derive(Subcommand, Debug)]
 pub enum MukdukCommands {
     #[clap(subcommand)]
     /// Commands for managing projects.
     Project(ProjectSubcommand),
+    #[clap(subcommand)]
+    /// Commands for live multiplexer sessions.
+    Session(SessionSubcommand),
+    /// Pick from live sessions and projects in one list, switching to or opening the pick.
+    ///
+    /// Running sessions are marked with `●` and their multiplexer, projects without a session
+    /// with `○` and their tags.
+    Switch(SwitchArgs),
+    #[clap(subcommand)]
+    /// Commands for managing the mukduk config file.
+    Config(ConfigSubcommand),
+    /// Print a completion script for your shell.
+    ///
+    /// Project and session names are completed live, so the script only needs to be set up once:
+    /// `source <(mukduk completions bash)` in your shell's rc file.
+    Completions(CompletionsArgs),
+    #[clap(subcommand)]
+    /// Print or install multiplexer key bindings that run mukduk.
+    Integrate(IntegrateSubcommand),
+    /// Generate man pages or a markdown CLI reference, used when packaging releases.
+    #[command(hide = true)]
+    Generate(GenerateArgs),
 }
 
 impl MukdukCommands {
-    pub fn handle_cmd(mukduk_command: Self, projects_dir: PathBuf) -> anyhow::Result<()> {
+    pub fn handle_cmd(mukduk_command: Self, context: &MukdukContext) -> anyhow::Result<()> {
         match mukduk_command {
             Self::Project(project_sub_cmd) => {
-                ProjectSubcommand::handle_cmd(project_sub_cmd, projects_dir)
+                ProjectSubcommand::handle_cmd(project_sub_cmd, context)
+            }
+            Self::Session(session_sub_cmd) => {
+                SessionSubcommand::handle_cmd(session_sub_cmd, context)
+            }
+            Self::Switch(switch_args) => SwitchArgs::handle_cmd(switch_args, context),
+            Self::Config(config_sub_cmd) => ConfigSubcommand::handle_cmd(config_sub_cmd, context),
+            Self::Completions(completions_args) => CompletionsArgs::handle_cmd(completions_args),
+            Self::Integrate(integrate_sub_cmd) => {
+                IntegrateSubcommand::handle_cmd(integrate_sub_cmd)
             }
+            Self::Generate(generate_args) => GenerateArgs::handle_cmd(generate_args),
         }
     }
 }
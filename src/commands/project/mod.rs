@@ -1,12 +1,33 @@
 use clap::{Args, Subcommand, ValueEnum};
+use clap_complete::ArgValueCandidates;
+use colored::Colorize;
 use git_lib::repo::GitRepo;
 use std::path::PathBuf;
 
+use super::{
+    completions::{
+        complete_archived, complete_project_dirs, complete_project_names, complete_sessions,
+        complete_tags, complete_templates,
+    },
+    session::rename_session,
+};
 use crate::{
+    cli::MukdukContext,
     config::ConfigEnvKey,
-    helper::{fzf_get_sessions, get_project, get_projects},
-    multiplexer::{Multiplexer, Multiplexers},
+    details::{Detail, LiveSessions, ProjectDetails},
+    error::MukdukError,
+    git::{Git, GitOp, GitReport},
+    helper::{
+        check_session_collision, child_dir, confirm, dir_name, find_project, fzf_get_sessions,
+        get_local_projects, get_project, get_projects, move_dir, parallel_map,
+        sessions_with_unsaved_work, with_session_command,
+    },
+    multiplexer::{BackendOptions, Multiplexer, Multiplexers, SanitizePolicy},
+    output::OutputArgs,
     project::Project,
+    runner::SystemRunner,
+    scaffold::scaffold,
+    state::{open_recorded, update_state},
 };
 
 #[derive(Args, Debug)]
@@ -14,15 +35,46 @@ pub struct SessionArgs {
     #[arg(short, long)]
     /// Which multiplexer session should be created.
     pub multiplexer: Multiplexers,
+
+    #[arg(short, long)]
+    /// Create the session in the background without attaching or switching to it.
+    pub detach: bool,
+}
+
+impl SessionArgs {
+    pub fn backend(&self, context: &MukdukContext) -> Box<dyn Multiplexer> {
+        self.multiplexer.backend(BackendOptions {
+            detach: self.detach,
+            tmux_socket: context.tmux_socket.clone(),
+            sanitize: self.sanitize_policy(context),
+        })
+    }
+
+    /// Open `project` with these args, remembering the switch for `switch --last`.
+    pub fn open(
+        &self,
+        context: &MukdukContext,
+        proj_args: &ProjectArgs,
+        project: Project,
+    ) -> anyhow::Result<()> {
+        let backend = self.backend(context);
+        open_recorded(context, self.multiplexer, self.detach, project, |project| {
+            backend.open(proj_args, project)
+        })
+    }
+
+    pub fn sanitize_policy(&self, context: &MukdukContext) -> SanitizePolicy {
+        context.config.sanitize_policy(self.multiplexer)
+    }
 }
 
 #[derive(Args, Debug)]
 pub struct ProjectArgs {
-    #[arg(short, long)]
+    #[arg(short, long, add = ArgValueCandidates::new(complete_project_names))]
     /// Name of session, defaults to project_dir name
     pub name: Option<String>,
 
-    #[arg(short, long)]
+    #[arg(short, long, add = ArgValueCandidates::new(complete_project_dirs))]
     /// Name of session, defaults to project_dir name
     pub project_dir: Option<PathBuf>,
 }
@@ -31,8 +83,18 @@ pub struct ProjectArgs {
 pub enum ProjectSubcommand {
     /// Open a session.
     Open {
+        #[arg(conflicts_with = "project_dir", add = ArgValueCandidates::new(complete_project_names))]
+        /// Open the project matching this directly, only showing the picker when it is ambiguous.
+        query: Option<String>,
         #[clap(flatten)]
         proj_args: ProjectArgs,
+        #[arg(short, long = "tag", add = ArgValueCandidates::new(complete_tags))]
+        /// Only pick from projects with this tag, repeat to require several.
+        tags: Vec<String>,
+        #[arg(long)]
+        /// Run the session's shells inside of the project's devcontainer or compose service,
+        /// starting it when it is not running.
+        container: bool,
         #[clap(flatten)]
         sess_args: SessionArgs,
     },
@@ -45,8 +107,12 @@ pub enum ProjectSubcommand {
     },
     /// Kill sessions.
     Kill {
-        #[clap(flatten)]
-        proj_args: ProjectArgs,
+        #[arg(short, long, add = ArgValueCandidates::new(complete_sessions))]
+        /// Name of the session to kill, skips the picker.
+        name: Option<String>,
+        #[arg(short, long)]
+        /// Kill sessions without asking, even when their project has uncommitted or unpushed work.
+        force: bool,
         #[clap(flatten)]
         sess_args: SessionArgs,
     },
@@ -59,8 +125,66 @@ pub enum ProjectSubcommand {
     },
     /// List all projects in your projects dir.
     List {
-        #[arg(short, long, value_enum, default_value_t=OutputFormat::Debug)]
-        output: OutputFormat,
+        #[clap(flatten)]
+        output: OutputArgs,
+        #[arg(short, long = "tag", add = ArgValueCandidates::new(complete_tags))]
+        /// Only list projects with this tag, repeat to require several.
+        tags: Vec<String>,
+        #[arg(short, long, value_enum, value_delimiter = ',')]
+        /// Extra details to collect for each project, like `--with git,lang`.
+        with: Vec<Detail>,
+    },
+    /// Run a git operation in every project that is a git repo, a few at a time.
+    Git {
+        #[arg(value_enum)]
+        op: GitOp,
+        #[arg(short, long)]
+        /// Only include projects whose name matches this glob, like 'api-*'.
+        filter: Option<String>,
+        #[arg(short, long = "tag", add = ArgValueCandidates::new(complete_tags))]
+        /// Only include projects with this tag, repeat to require several.
+        tags: Vec<String>,
+        #[arg(short, long, default_value_t = 8)]
+        /// How many repos to work on at once.
+        jobs: usize,
+        #[clap(flatten)]
+        output: OutputArgs,
+    },
+    /// Create a new project in your projects dir, optionally from a template, and open it.
+    Create {
+        /// Directory name of the new project.
+        name: String,
+        #[arg(short, long, add = ArgValueCandidates::new(complete_templates))]
+        /// Template from the config file to copy, replacing `{{name}}` and `{{path}}`.
+        template: Option<String>,
+        #[clap(flatten)]
+        sess_args: SessionArgs,
+    },
+    /// Move a project into `archive_dir`, killing its sessions first.
+    Archive {
+        #[arg(add = ArgValueCandidates::new(complete_project_names))]
+        /// Project to archive, by session or directory name.
+        name: String,
+        #[arg(long)]
+        /// Delete the project instead of moving it to `archive_dir`.
+        delete: bool,
+        #[arg(short, long)]
+        /// Skip checking for uncommitted or unpushed work, and confirming `--delete`.
+        force: bool,
+    },
+    /// Rename a project's directory, along with its live sessions.
+    Rename {
+        #[arg(add = ArgValueCandidates::new(complete_project_names))]
+        /// Project to rename, by session or directory name.
+        name: String,
+        /// New directory name, next to the current one.
+        new_name: String,
+    },
+    /// Move an archived project back into your projects dir.
+    Restore {
+        #[arg(add = ArgValueCandidates::new(complete_archived))]
+        /// Directory name of the archived project.
+        name: String,
     },
     /// Clone a new repo into your projects dir.
     New {
@@ -71,69 +195,96 @@ pub enum ProjectSubcommand {
        // Harpoon(ProjectArgs),
 }
 
-#[derive(ValueEnum, Debug, Clone)]
-pub enum OutputFormat {
-    /// Rust Debug print.
-    Debug,
-    /// Pretty printed json.
-    Json,
-    /// Raw printed json.
-    JsonR,
-    /// yaml.
-    Yaml,
-}
-
 impl ProjectSubcommand {
-    pub fn handle_cmd(project_sub_cmd: Self, projects_dir: PathBuf) -> anyhow::Result<()> {
+    pub fn handle_cmd(project_sub_cmd: Self, context: &MukdukContext) -> anyhow::Result<()> {
         match project_sub_cmd {
             Self::Open {
+                query,
                 proj_args,
+                tags,
+                container,
                 sess_args,
             } => {
-                let project =
-                    get_project(projects_dir, &proj_args.project_dir, proj_args.name.clone())?;
-                sess_args.multiplexer.open(&proj_args, project)?;
-                Ok(())
+                // An explicit `-n` is taken as the user knowing which session they want, so
+                // there is nothing to list when a path is given along with it.
+                let projects = if proj_args.project_dir.is_some() && proj_args.name.is_some() {
+                    Vec::new()
+                } else {
+                    get_projects(&context.projects_dir()?, &context.config, &[])?
+                };
+                let project = get_project(
+                    &projects,
+                    &context.config,
+                    &proj_args.project_dir,
+                    proj_args.name.clone(),
+                    &tags,
+                    query.as_deref(),
+                )?;
+                if proj_args.name.is_none() {
+                    check_session_collision(
+                        &project,
+                        &projects,
+                        &sess_args.sanitize_policy(context),
+                    )?;
+                }
+                let project = with_session_command(&context.config, project, container)?;
+                sess_args.open(context, &proj_args, project)
             }
             Self::Scratch {
                 proj_args,
                 sess_args,
             } => {
-                sess_args.multiplexer.open(
-                    &proj_args,
-                    Project::new(
-                        proj_args
-                            .project_dir
-                            .clone()
-                            .unwrap_or(PathBuf::try_from(ConfigEnvKey::Home)?),
-                        proj_args
-                            .name
-                            .clone()
-                            .unwrap_or_else(|| "scratch".to_string()),
-                    ),
-                )?;
-                Ok(())
+                let project = Project::new(
+                    proj_args
+                        .project_dir
+                        .clone()
+                        .unwrap_or(PathBuf::try_from(ConfigEnvKey::Home)?),
+                    proj_args
+                        .name
+                        .clone()
+                        .unwrap_or_else(|| "scratch".to_string()),
+                );
+                sess_args.open(context, &proj_args, project)
             }
             Self::Kill {
-                proj_args: _,
+                name,
+                force,
                 sess_args,
             } => {
-                let sessions = sess_args.multiplexer.get_sessions();
-                log::debug!("sessions: {sessions:?}");
-                let picked_sessions = fzf_get_sessions(sessions)?;
-                sess_args.multiplexer.kill_sessions(picked_sessions)?;
+                let backend = sess_args.backend(context);
+                let picked_sessions = match name {
+                    Some(name) => vec![name],
+                    None => {
+                        let sessions = backend.get_sessions()?;
+                        log::debug!("sessions: {sessions:?}");
+                        fzf_get_sessions(sessions, &context.config.picker)?
+                    }
+                };
+                let picked_sessions = if force {
+                    picked_sessions
+                } else {
+                    confirm_unsaved_work(picked_sessions, &sess_args, context)?
+                };
+                backend.kill_sessions(picked_sessions)?;
                 Ok(())
             }
             Self::Home {
-                proj_args: _,
+                proj_args,
                 sess_args,
-            } => sess_args.multiplexer.unique_session(),
+            } => {
+                let Some(name) = sess_args.backend(context).free_unique_session()? else {
+                    log::warn!("All unique sessions (0-9) are already open.");
+                    return Ok(());
+                };
+                let project = Project::new(PathBuf::try_from(ConfigEnvKey::Home)?, name);
+                sess_args.open(context, &proj_args, project)
+            }
             Self::New {
                 proj_args: _,
                 ssh_uri,
             } => {
                 log::debug!("Attempting to clone {ssh_uri}...");
-                let results = GitRepo::from_ssh_uri_multi(&[&ssh_uri], &projects_dir);
+                let results = GitRepo::from_ssh_uri_multi(&[&ssh_uri], &context.projects_dir()?);
                 for result in results {
                     if let Err(err) = result {
                         log::error!("Failed cloning with: {err:?}");
@@ -141,22 +292,304 @@ impl ProjectSubcommand {
                 }
                 Ok(())
             }
-            Self::List { output } => {
-                let projects = get_projects(&projects_dir)?;
-                match output {
-                    OutputFormat::Debug => {
-                        println!("{:#?}", projects);
+            Self::Git {
+                op,
+                filter,
+                tags,
+                jobs,
+                output,
+            } => {
+                let pattern = filter.as_deref().map(glob::Pattern::new).transpose()?;
+                let projects: Vec<_> =
+                    get_projects(&context.projects_dir()?, &context.config, &tags)?
+                        .into_iter()
+                        .filter(|project| Git::is_repo(&project.path))
+                        .filter(|project| {
+                            pattern
+                                .as_ref()
+                                .is_none_or(|pattern| pattern.matches(&project.name))
+                        })
+                        .collect();
+                let reports = parallel_map(&projects, jobs, |project| {
+                    let result = Git::new(&SystemRunner).run(op, &project.path);
+                    GitReport::new(project.clone(), result)
+                });
+                output.print(&reports)?;
+                GitReport::summarize(&reports)?;
+                Ok(())
+            }
+            Self::Create {
+                name,
+                template,
+                sess_args,
+            } => {
+                let projects_dir = context.projects_dir()?;
+                let path = child_dir(&projects_dir, &name)?;
+                match template {
+                    Some(template) => {
+                        let template_dir =
+                            context.config.templates.get(&template).ok_or_else(|| {
+                                MukdukError::UnknownProjectTemplate {
+                                    name: template.clone(),
+                                    available: context
+                                        .config
+                                        .templates
+                                        .keys()
+                                        .cloned()
+                                        .collect::<Vec<_>>()
+                                        .join(", "),
+                                }
+                            })?;
+                        let path_var = path.to_string_lossy();
+                        scaffold(template_dir, &path, &[("name", &name), ("path", &path_var)])?;
                     }
-                    OutputFormat::Json => {
-                        println!("{}", serde_json::to_string_pretty(&projects)?)
+                    None if path.exists() => {
+                        return Err(MukdukError::AlreadyExists(path).into());
                     }
-                    OutputFormat::Yaml => println!("{}", serde_yaml::to_string(&projects)?),
-                    OutputFormat::JsonR => {
-                        println!("{}", serde_json::to_string(&projects)?)
+                    None => std::fs::create_dir_all(&path)?,
+                }
+                Git::new(&SystemRunner).init(&path)?;
+
+                let proj_args = ProjectArgs {
+                    name: None,
+                    project_dir: Some(path),
+                };
+                let project = get_project(
+                    &[],
+                    &context.config,
+                    &proj_args.project_dir,
+                    None,
+                    &[],
+                    None,
+                )?;
+                let project = with_session_command(&context.config, project, false)?;
+                sess_args.open(context, &proj_args, project)
+            }
+            Self::Archive {
+                name,
+                delete,
+                force,
+            } => {
+                let projects_dir = context.projects_dir()?;
+                let project =
+                    find_project(get_local_projects(&projects_dir, &context.config)?, &name)?;
+                if !force {
+                    check_archivable(&project)?;
+                }
+                let destination = if delete {
+                    if !force
+                        && !confirm(
+                            &format!("Delete {}?", project.path.to_string_lossy()),
+                            "delete it",
+                        )?
+                    {
+                        return Ok(());
+                    }
+                    None
+                } else {
+                    let archive_dir = context
+                        .config
+                        .archive_dir
+                        .clone()
+                        .ok_or(MukdukError::MissingConfigKey("archive_dir"))?;
+                    let destination = archive_dir.join(dir_name(&project.path)?);
+                    if destination.exists() {
+                        return Err(MukdukError::AlreadyExists(destination).into());
+                    }
+                    Some(destination)
+                };
+
+                kill_project_sessions(&project, context)?;
+                // Its sessions are gone, so `switch --last` must not bring the project back.
+                update_state(|state| state.forget_path(&project.path));
+                match destination {
+                    Some(destination) => {
+                        move_dir(&project.path, &destination)?;
+                        println!(
+                            "{} {} to {}",
+                            "Archived".green().bold(),
+                            project.name,
+                            destination.to_string_lossy()
+                        );
+                    }
+                    None => {
+                        std::fs::remove_dir_all(&project.path)?;
+                        println!("{} {}", "Deleted".green().bold(), project.name);
+                    }
+                }
+                Ok(())
+            }
+            Self::Rename { name, new_name } => {
+                let projects_dir = context.projects_dir()?;
+                let project =
+                    find_project(get_local_projects(&projects_dir, &context.config)?, &name)?;
+                let destination = child_dir(&projects_dir, &new_name)?;
+                if destination.exists() {
+                    return Err(MukdukError::AlreadyExists(destination).into());
+                }
+
+                let old_dir_name = dir_name(&project.path)?;
+                // Settings keyed by the old name or path stop applying once the directory moves.
+                if context.config.projects.contains_key(&old_dir_name)
+                    || context
+                        .config
+                        .projects
+                        .contains_key(&*project.path.to_string_lossy())
+                {
+                    eprintln!(
+                        "{} settings for {old_dir_name} under `projects` in your config file need to be renamed too.",
+                        "warning:".yellow().bold()
+                    );
+                }
+
+                std::fs::rename(&project.path, &destination)?;
+                update_state(|state| state.move_path(&project.path, &destination));
+                println!(
+                    "{} {} to {}",
+                    "Renamed".green().bold(),
+                    project.path.to_string_lossy(),
+                    destination.to_string_lossy()
+                );
+
+                // Sessions follow the project's new name, which only stays the same when the
+                // project's own `.mukduk.toml` sets an alias.
+                let new_project_name = context
+                    .config
+                    .project_settings(&new_name, &destination)
+                    .alias
+                    .unwrap_or_else(|| new_name.clone());
+                for &multiplexer in Multiplexers::value_variants() {
+                    let sanitize = context.config.sanitize_policy(multiplexer);
+                    let (from, to) = (
+                        sanitize.apply(&project.name),
+                        sanitize.apply(&new_project_name),
+                    );
+                    // The directory has already moved, so a session that could not follow is
+                    // only worth a warning.
+                    if from != to {
+                        if let Err(err) = rename_session(context, multiplexer, &from, &to) {
+                            eprintln!(
+                                "{} could not rename session {from} to {to}: {err}",
+                                "warning:".yellow().bold()
+                            );
+                        }
                     }
                 }
                 Ok(())
             }
+            Self::Restore { name } => {
+                let archive_dir = context
+                    .config
+                    .archive_dir
+                    .clone()
+                    .ok_or(MukdukError::MissingConfigKey("archive_dir"))?;
+                let archived = child_dir(&archive_dir, &name)?;
+                if !archived.is_dir() {
+                    return Err(MukdukError::ProjectNotFound(name).into());
+                }
+                let destination = child_dir(&context.projects_dir()?, &name)?;
+                move_dir(&archived, &destination)?;
+                println!(
+                    "{} {name} to {}",
+                    "Restored".green().bold(),
+                    destination.to_string_lossy()
+                );
+                Ok(())
+            }
+            Self::List { output, tags, with } => {
+                let live = if with.contains(&Detail::Sessions) {
+                    LiveSessions::collect(context)
+                } else {
+                    LiveSessions::default()
+                };
+                let git = Git::new(&SystemRunner);
+                let projects: Vec<_> =
+                    get_projects(&context.projects_dir()?, &context.config, &tags)?
+                        .into_iter()
+                        .map(|project| ProjectDetails::collect(project, &with, &git, &live))
+                        .collect();
+                output.print(&projects)?;
+                Ok(())
+            }
         }
     }
 }
+
+/// Refuse to archive projects with work that only exists locally.
+fn check_archivable(project: &Project) -> anyhow::Result<()> {
+    let Some(status) = Git::new(&SystemRunner).status(&project.path)? else {
+        return Ok(());
+    };
+    let mut problems = status.unsaved_work();
+    if status.branch.is_some() && status.ahead.is_none() {
+        problems.push("no upstream branch".to_string());
+    }
+    if problems.is_empty() {
+        return Ok(());
+    }
+    Err(MukdukError::UnsavedWork {
+        name: project.name.clone(),
+        problems: problems.join(" and "),
+    }
+    .into())
+}
+
+/// Kill the project's session in every multiplexer that has one.
+fn kill_project_sessions(project: &Project, context: &MukdukContext) -> anyhow::Result<()> {
+    for &multiplexer in Multiplexers::value_variants() {
+        let sanitize = context.config.sanitize_policy(multiplexer);
+        let session = sanitize.apply(&project.name);
+        let backend = multiplexer.backend(BackendOptions {
+            tmux_socket: context.tmux_socket.clone(),
+            sanitize,
+            ..Default::default()
+        });
+        // Multiplexers that are not installed or running have no sessions to kill.
+        if backend
+            .get_sessions()
+            .is_ok_and(|sessions| sessions.contains(&session))
+        {
+            backend.kill_sessions(vec![session])?;
+        }
+    }
+    Ok(())
+}
+
+/// Warn about sessions whose project has work that was never committed or pushed, and only keep
+/// them when the user confirms. Without a terminal to ask on they are kept alive.
+fn confirm_unsaved_work(
+    sessions: Vec<String>,
+    sess_args: &SessionArgs,
+    context: &MukdukContext,
+) -> anyhow::Result<Vec<String>> {
+    let Ok(projects_dir) = context.projects_dir() else {
+        return Ok(sessions);
+    };
+    let projects = get_projects(&projects_dir, &context.config, &[])?;
+    let unsaved = sessions_with_unsaved_work(
+        &sessions,
+        &projects,
+        &sess_args.sanitize_policy(context),
+        &Git::new(&SystemRunner),
+    );
+    if unsaved.is_empty() {
+        return Ok(sessions);
+    }
+
+    for (session, project, status) in &unsaved {
+        eprintln!(
+            "{} session `{session}` ({}) has {}",
+            "warning:".yellow().bold(),
+            project.path.to_string_lossy(),
+            status.unsaved_work().join(" and ")
+        );
+    }
+    let confirmed = confirm("Kill these sessions anyway?", "kill them")?;
+    if confirmed {
+        return Ok(sessions);
+    }
+    Ok(sessions
+        .into_iter()
+        .filter(|session| !unsaved.iter().any(|(unsaved, ..)| unsaved == session))
+        .collect())
+}
@@ -0,0 +1,138 @@
+use clap::{Subcommand, ValueEnum};
+use clap_complete::ArgValueCandidates;
+use colored::Colorize;
+use serde::Serialize;
+
+use super::completions::complete_sessions;
+use crate::{
+    cli::MukdukContext,
+    error::MukdukError,
+    multiplexer::{BackendOptions, Multiplexers},
+    output::{OutputArgs, Tabular},
+    state::update_state,
+};
+
+#[derive(Subcommand, Debug)]
+pub enum SessionSubcommand {
+    /// List live sessions.
+    List {
+        #[arg(short, long)]
+        /// Only list sessions of this multiplexer, defaults to every one that is running.
+        multiplexer: Option<Multiplexers>,
+        #[clap(flatten)]
+        output: OutputArgs,
+    },
+    /// Rename a live session, in every multiplexer that has one by that name.
+    Rename {
+        #[arg(add = ArgValueCandidates::new(complete_sessions))]
+        /// Current name of the session.
+        from: String,
+        /// New name, with the same characters replaced as in project session names.
+        to: String,
+        #[arg(short, long)]
+        /// Only rename the session of this multiplexer.
+        multiplexer: Option<Multiplexers>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Session {
+    pub name: String,
+    pub multiplexer: Multiplexers,
+}
+
+impl Tabular for Session {
+    fn headers(&self) -> Vec<&'static str> {
+        vec!["name", "multiplexer"]
+    }
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.name.clone(),
+            format!("{:?}", self.multiplexer).to_lowercase(),
+        ]
+    }
+}
+
+impl SessionSubcommand {
+    pub fn handle_cmd(session_sub_cmd: Self, context: &MukdukContext) -> anyhow::Result<()> {
+        match session_sub_cmd {
+            Self::List {
+                multiplexer,
+                output,
+            } => {
+                let mut sessions = Vec::new();
+                for &candidate in Multiplexers::value_variants() {
+                    if multiplexer.is_some_and(|m| m != candidate) {
+                        continue;
+                    }
+                    let backend = candidate.backend(BackendOptions {
+                        tmux_socket: context.tmux_socket.clone(),
+                        ..Default::default()
+                    });
+                    let names = match backend.get_sessions() {
+                        Ok(names) => names,
+                        // Only an explicitly requested multiplexer has to be available.
+                        Err(err) if multiplexer.is_none() => {
+                            log::debug!("skipping {candidate:?} sessions: {err}");
+                            continue;
+                        }
+                        Err(err) => return Err(err),
+                    };
+                    sessions.extend(names.into_iter().map(|name| Session {
+                        name,
+                        multiplexer: candidate,
+                    }));
+                }
+                output.print(&sessions)?;
+                Ok(())
+            }
+            Self::Rename {
+                from,
+                to,
+                multiplexer,
+            } => {
+                let mut renamed = false;
+                for &candidate in Multiplexers::value_variants() {
+                    if multiplexer.is_none_or(|m| m == candidate) {
+                        let to = context.config.sanitize_policy(candidate).apply(&to);
+                        renamed |= rename_session(context, candidate, &from, &to)?;
+                    }
+                }
+                if !renamed {
+                    return Err(MukdukError::SessionNotFound(from).into());
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Rename the `from` session when `multiplexer` has one, keeping the state file in step. Returns
+/// whether there was a session to rename.
+pub fn rename_session(
+    context: &MukdukContext,
+    multiplexer: Multiplexers,
+    from: &str,
+    to: &str,
+) -> anyhow::Result<bool> {
+    let backend = multiplexer.backend(BackendOptions {
+        tmux_socket: context.tmux_socket.clone(),
+        ..Default::default()
+    });
+    // Multiplexers that are not installed or running have no sessions to rename.
+    if !backend
+        .get_sessions()
+        .is_ok_and(|sessions| sessions.iter().any(|session| session == from))
+    {
+        return Ok(false);
+    }
+    backend.rename_session(from, to)?;
+    update_state(|state| state.rename_session(multiplexer, from, to));
+    println!(
+        "{} {} session {from} to {to}",
+        "Renamed".green().bold(),
+        format!("{multiplexer:?}").to_lowercase()
+    );
+    Ok(true)
+}
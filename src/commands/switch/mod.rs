@@ -0,0 +1,271 @@
+use anyhow::Result;
+use clap::Args;
+use clap_complete::ArgValueCandidates;
+use colored::Colorize;
+use std::path::PathBuf;
+
+use super::{completions::complete_tags, project::ProjectArgs};
+use crate::{
+    cli::MukdukContext,
+    config::ConfigEnvKey,
+    details::LiveSessions,
+    error::MukdukError,
+    fzf::FzfCmd,
+    helper::{get_projects, with_session_command},
+    multiplexer::{BackendOptions, Multiplexers},
+    project::Project,
+    state::{current_session, open_recorded, State},
+};
+
+#[derive(Args, Debug)]
+pub struct SwitchArgs {
+    #[arg(short, long)]
+    /// Only list sessions of this multiplexer, and open projects with it. Defaults to every
+    /// running multiplexer, opening projects with the one mukduk is run inside of.
+    pub multiplexer: Option<Multiplexers>,
+
+    #[arg(short, long = "tag", add = ArgValueCandidates::new(complete_tags))]
+    /// Only list projects with this tag, repeat to require several.
+    pub tags: Vec<String>,
+
+    #[arg(short, long)]
+    /// Create the session in the background without attaching or switching to it.
+    pub detach: bool,
+
+    #[arg(short, long, conflicts_with = "tags")]
+    /// Go back to the session mukduk last switched away from, like `tmux switch-client -l`.
+    pub last: bool,
+}
+
+/// One line of the `switch` picker.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SwitchTarget {
+    /// A running session, with the project it belongs to when there is one.
+    Session {
+        multiplexer: Multiplexers,
+        name: String,
+        project: Option<Project>,
+    },
+    /// A project that does not have a running session yet.
+    Project(Project),
+}
+
+impl SwitchTarget {
+    /// Running sessions first, then every project that none of them belong to.
+    pub fn collect(live: &LiveSessions, projects: Vec<Project>) -> Vec<Self> {
+        let mut targets: Vec<Self> = live
+            .sessions()
+            .map(|(multiplexer, sanitize, name)| Self::Session {
+                multiplexer,
+                name: name.to_string(),
+                project: projects
+                    .iter()
+                    .find(|project| sanitize.apply(&project.name) == name)
+                    .cloned(),
+            })
+            .collect();
+        let open: Vec<_> = targets
+            .iter()
+            .filter_map(|target| match target {
+                Self::Session {
+                    project: Some(project),
+                    ..
+                } => Some(project.path.clone()),
+                _ => None,
+            })
+            .collect();
+        targets.extend(
+            projects
+                .into_iter()
+                .filter(|project| !open.contains(&project.path))
+                .map(Self::Project),
+        );
+        targets
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Session { name, .. } => name,
+            Self::Project(project) => &project.name,
+        }
+    }
+
+    /// `●` marks running sessions along with their multiplexer, `○` marks projects along with
+    /// their tags.
+    fn picker_line(&self) -> String {
+        match self {
+            Self::Session { multiplexer, .. } => {
+                format!(
+                    "● {}\t{}",
+                    self.name(),
+                    format!("{multiplexer:?}").to_lowercase()
+                )
+            }
+            Self::Project(project) => format!("○ {}\t{}", self.name(), project.tags.join(",")),
+        }
+    }
+}
+
+impl SwitchArgs {
+    pub fn handle_cmd(switch_args: Self, context: &MukdukContext) -> Result<()> {
+        if switch_args.last {
+            let state = State::load(&State::path()?);
+            let previous = state
+                .previous(current_session(context).as_ref())
+                .ok_or(MukdukError::NoPreviousSession)?
+                .clone();
+            let target = SwitchTarget::Session {
+                project: previous
+                    .path
+                    .map(|path| Project::new(path, previous.name.clone())),
+                multiplexer: previous.multiplexer,
+                name: previous.name,
+            };
+            return switch_args.open(target, context);
+        }
+
+        let mut live = LiveSessions::collect(context);
+        if let Some(multiplexer) = switch_args.multiplexer {
+            live = live.only(multiplexer);
+        }
+        let projects = get_projects(&context.projects_dir()?, &context.config, &switch_args.tags)?;
+        let targets = SwitchTarget::collect(&live, projects);
+        let target = pick_target(&targets, context)?;
+        switch_args.open(target.clone(), context)
+    }
+
+    fn open(&self, target: SwitchTarget, context: &MukdukContext) -> Result<()> {
+        let (multiplexer, project) = match target {
+            SwitchTarget::Session {
+                multiplexer,
+                name,
+                project,
+            } => match project {
+                Some(project) => (
+                    multiplexer,
+                    with_session_command(&context.config, project, false)?,
+                ),
+                // Sessions mukduk did not open from a project already exist, so the path is not
+                // used for anything.
+                None => (
+                    multiplexer,
+                    Project::new(PathBuf::try_from(ConfigEnvKey::Home)?, name),
+                ),
+            },
+            SwitchTarget::Project(project) => (
+                self.multiplexer
+                    .or_else(Multiplexers::current)
+                    .ok_or_else(|| MukdukError::NoMultiplexer(project.name.clone()))?,
+                with_session_command(&context.config, project, false)?,
+            ),
+        };
+        // zellij can not switch sessions from the cli, so from inside of it the session is only
+        // opened in the background for the user to switch to.
+        let detach = self.detach
+            || (multiplexer == Multiplexers::Zellij
+                && Multiplexers::current() == Some(Multiplexers::Zellij));
+        let backend = multiplexer.backend(BackendOptions {
+            detach,
+            tmux_socket: context.tmux_socket.clone(),
+            sanitize: context.config.sanitize_policy(multiplexer),
+        });
+        let proj_args = ProjectArgs {
+            name: None,
+            project_dir: Some(project.path.clone()),
+        };
+        let name = context
+            .config
+            .sanitize_policy(multiplexer)
+            .apply(&project.name);
+        open_recorded(context, multiplexer, detach, project, |project| {
+            backend.open(&proj_args, project)
+        })?;
+        if detach && !self.detach {
+            eprintln!(
+                "{} `{name}` is open, switch to it with zellij's session manager.",
+                "hint:".yellow().bold()
+            );
+        }
+        Ok(())
+    }
+}
+
+fn pick_target<'a>(
+    targets: &'a [SwitchTarget],
+    context: &MukdukContext,
+) -> Result<&'a SwitchTarget> {
+    // Lines are prefixed with their index, which fzf hides, so picking does not depend on names
+    // being unique across multiplexers.
+    let name_width = targets.iter().map(|t| t.name().len()).max().unwrap_or(0);
+    let picker_lines = targets
+        .iter()
+        .enumerate()
+        .map(|(index, target)| format!("{index}\t{}", target.picker_line()))
+        .collect::<Vec<_>>();
+    let picked = FzfCmd::new()
+        .picker(&context.config.picker)
+        .args(["--delimiter", "\t", "--with-nth", "2.."])
+        .args([format!("--tabstop={}", name_width + 4)])
+        .find_vec(picker_lines)?;
+    picked
+        .split('\t')
+        .next()
+        .and_then(|index| index.parse::<usize>().ok())
+        .and_then(|index| targets.get(index))
+        .ok_or_else(|| MukdukError::NoSelection("session").into())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use pretty_assertions::assert_eq;
+
+    use super::SwitchTarget;
+    use crate::{details::LiveSessions, multiplexer::Multiplexers, project::Project};
+
+    #[test]
+    fn should_list_sessions_then_projects_without_one() {
+        let api = Project::new(PathBuf::from("/p/api.v2"), "api.v2".to_string());
+        let web = Project::new(PathBuf::from("/p/web"), "web".to_string())
+            .with_tags(vec!["work".to_string()]);
+        let live = LiveSessions::new(vec![
+            (
+                Multiplexers::Tmux,
+                Multiplexers::Tmux.default_sanitize(),
+                vec!["api_v2".to_string(), "scratch".to_string()],
+            ),
+            (
+                Multiplexers::Zellij,
+                Multiplexers::Zellij.default_sanitize(),
+                vec!["scratch".to_string()],
+            ),
+        ]);
+
+        let targets = SwitchTarget::collect(&live, vec![api.clone(), web.clone()]);
+
+        assert_eq!(
+            targets,
+            vec![
+                SwitchTarget::Session {
+                    multiplexer: Multiplexers::Tmux,
+                    name: "api_v2".to_string(),
+                    project: Some(api),
+                },
+                SwitchTarget::Session {
+                    multiplexer: Multiplexers::Tmux,
+                    name: "scratch".to_string(),
+                    project: None,
+                },
+                SwitchTarget::Session {
+                    multiplexer: Multiplexers::Zellij,
+                    name: "scratch".to_string(),
+                    project: None,
+                },
+                SwitchTarget::Project(web),
+            ]
+        );
+        assert_eq!(targets[2].picker_line(), "● scratch\tzellij");
+        assert_eq!(targets[3].picker_line(), "○ web\twork");
+    }
+}
@@ -1,28 +1,293 @@
 use anyhow::Result;
-use std::{env, fs, path::PathBuf};
+use clap::ValueEnum;
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+};
 
 use serde::{Deserialize, Serialize};
-use thiserror::Error;
+
+use crate::{
+    container::ContainerSettings,
+    environment::Environment,
+    error::MukdukError,
+    multiplexer::{tmux::TmuxSocket, Multiplexers, SanitizePolicy},
+    remote::Remote,
+    sources::ProjectSource,
+};
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct MukdukConfig {
+    #[serde(default)]
     pub projects_dir: ProjectsDir,
+    /// Where `project archive` moves projects to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archive_dir: Option<PathBuf>,
+    #[serde(default)]
+    pub tmux: TmuxConfig,
+    #[serde(default)]
+    pub zellij: ZellijConfig,
+    #[serde(default)]
+    pub picker: PickerConfig,
+    /// Directories `project create --template` copies from, keyed by template name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub templates: BTreeMap<String, PathBuf>,
+    /// Places other than the projects dir to find projects in.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sources: Vec<ProjectSource>,
+    /// Per project settings, keyed by project name or by the full path to the project.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub projects: BTreeMap<String, ProjectSettings>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct ProjectsDir {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub default: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub options: Option<Vec<PathBuf>>,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct TmuxConfig {
+    /// Name of the server socket, same as `tmux -L`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub socket_name: Option<String>,
+    /// Path to the server socket, same as `tmux -S`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub socket_path: Option<PathBuf>,
+    /// Overrides how project names are turned into session names.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sanitize: Option<SanitizePolicy>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ZellijConfig {
+    /// Overrides how project names are turned into session names.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sanitize: Option<SanitizePolicy>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct PickerConfig {
+    /// Show pickers in a tmux popup when run inside of tmux, same as `--popup`.
+    #[serde(default)]
+    pub popup: bool,
+    /// Position and size of the popup as `fzf --tmux` takes it, like `center,80%,60%`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub popup_size: Option<String>,
+}
+
+impl PickerConfig {
+    pub const DEFAULT_POPUP_SIZE: &'static str = "center,80%,60%";
+
+    /// Popup to show pickers in, `None` when they should take over the current pane.
+    pub fn popup(&self) -> Option<&str> {
+        self.popup.then(|| {
+            self.popup_size
+                .as_deref()
+                .unwrap_or(Self::DEFAULT_POPUP_SIZE)
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProjectSettings {
+    /// Session and display name to use instead of the directory name.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
+    /// Free form labels like `work` or `rust`, used to filter projects.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// How `--container` runs the project's panes inside of its container.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub container: Option<ContainerSettings>,
+    /// Load the project's toolchain with direnv or nix in every shell of its sessions.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub env: Option<Environment>,
+}
+
+impl ProjectSettings {
+    /// Settings file that can be checked in at the root of a project.
+    pub const LOCAL_FILE: &'static str = ".mukduk.toml";
+
+    /// Read the project's own settings file, if it has one.
+    ///
+    /// The file comes with the repo, so `container` and `env` are dropped from it: only the
+    /// user's own config may decide what runs in the project's shells.
+    pub fn from_project_dir(project_dir: &Path) -> Result<Option<Self>> {
+        let path = project_dir.join(Self::LOCAL_FILE);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let mut settings: Self = toml::from_str(&fs::read_to_string(&path)?)?;
+        for (key, ignored) in [
+            ("container", settings.container.take().is_some()),
+            ("env", settings.env.take().is_some()),
+        ] {
+            if ignored {
+                log::warn!(
+                    "ignoring `{key}` in {}, set it in your config file instead",
+                    path.to_string_lossy()
+                );
+            }
+        }
+        Ok(Some(settings))
+    }
+
+    /// Add everything from `other` that is not already set.
+    pub fn merge(&mut self, other: Self) {
+        self.alias = self.alias.take().or(other.alias);
+        self.container = self.container.take().or(other.container);
+        self.env = self.env.or(other.env);
+        for tag in other.tags {
+            if !self.tags.contains(&tag) {
+                self.tags.push(tag);
+            }
+        }
+    }
+}
+
+impl TmuxConfig {
+    pub fn socket(&self) -> Result<Option<TmuxSocket>, MukdukError> {
+        match (&self.socket_name, &self.socket_path) {
+            (Some(_), Some(_)) => Err(MukdukError::ConflictingConfigKeys(
+                "tmux.socket_name",
+                "tmux.socket_path",
+            )),
+            (Some(name), None) => Ok(Some(TmuxSocket::Name(name.clone()))),
+            (None, Some(path)) => Ok(Some(TmuxSocket::Path(path.clone()))),
+            (None, None) => Ok(None),
+        }
+    }
+}
+
 impl MukdukConfig {
     pub fn from_file(config_path: &PathBuf) -> Result<Self> {
         log::trace!("loading config from {}...", config_path.to_string_lossy());
-        let loaded_config = toml::from_str(&fs::read_to_string(config_path)?)?;
+        let format = ConfigFormat::from_path(config_path)?;
+        let loaded_config = format.parse(&fs::read_to_string(config_path)?)?;
         log::trace!("config: {:#?}", loaded_config);
         log::trace!("config loaded!");
         Ok(loaded_config)
     }
+
+    /// Load the config from its default location without creating anything, for callers that
+    /// should not touch the filesystem (like shell completion).
+    pub fn load_default() -> Result<Self> {
+        let xdg_config = PathBuf::try_from(ConfigEnvKey::XDGConfig)?;
+        let found = if xdg_config.exists() {
+            Self::find_in(&xdg_config.join("mukduk"), "config")?
+        } else {
+            Self::find_in(&PathBuf::try_from(ConfigEnvKey::Home)?, ".mukdukrc")?
+        };
+        found.map_or_else(|| Ok(Self::default()), |path| Self::from_file(&path))
+    }
+
+    /// How project names become session names for `multiplexer`.
+    pub fn sanitize_policy(&self, multiplexer: Multiplexers) -> SanitizePolicy {
+        let configured = match multiplexer {
+            Multiplexers::Tmux => &self.tmux.sanitize,
+            Multiplexers::Zellij => &self.zellij.sanitize,
+        };
+        configured
+            .clone()
+            .unwrap_or_else(|| multiplexer.default_sanitize())
+    }
+
+    /// Settings for a project, combining the config entries for its name and path with the
+    /// project's own `.mukduk.toml`. A broken local file is skipped with a warning so one
+    /// project can not break listing all of them.
+    pub fn project_settings(&self, name: &str, path: &Path) -> ProjectSettings {
+        let mut settings = ProjectSettings::default();
+        for (key, configured) in &self.projects {
+            if key == name || Path::new(key) == path {
+                settings.merge(configured.clone());
+            }
+        }
+        // Remote projects have no local directory to keep a settings file in.
+        if Remote::from_path(path).is_some() {
+            return settings;
+        }
+        match ProjectSettings::from_project_dir(path) {
+            Ok(Some(local)) => settings.merge(local),
+            Ok(None) => {}
+            Err(err) => log::warn!(
+                "skipping {}: {err}",
+                path.join(ProjectSettings::LOCAL_FILE).to_string_lossy()
+            ),
+        }
+        settings
+    }
+
+    /// Look for `{stem}.{toml,yaml,yml,json}` inside of `dir`.
+    ///
+    /// Returns `None` when no config file exists, and an error when more than one does since
+    /// there is no way to tell which one should win.
+    pub fn find_in(dir: &Path, stem: &str) -> Result<Option<PathBuf>, MukdukError> {
+        let found: Vec<PathBuf> = ConfigFormat::EXTENSIONS
+            .iter()
+            .map(|ext| dir.join(format!("{stem}.{ext}")))
+            .filter(|path| path.is_file())
+            .collect();
+
+        match found.len() {
+            0 => Ok(None),
+            1 => Ok(found.into_iter().next()),
+            _ => Err(MukdukError::AmbiguousConfig(found)),
+        }
+    }
+}
+
+/// Formats that a config file can be written in. The format is picked by file extension.
+#[derive(ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Toml,
+    Yaml,
+    Json,
+}
+
+impl ConfigFormat {
+    /// Every extension that is recognized, in the order they are searched.
+    pub const EXTENSIONS: [&'static str; 4] = ["toml", "yaml", "yml", "json"];
+
+    pub fn from_path(path: &Path) -> Result<Self, MukdukError> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Ok(Self::Toml),
+            Some("yaml" | "yml") => Ok(Self::Yaml),
+            Some("json") => Ok(Self::Json),
+            _ => Err(MukdukError::UnsupportedConfigFormat(path.to_path_buf())),
+        }
+    }
+
+    pub const fn extension(&self) -> &'static str {
+        match self {
+            Self::Toml => "toml",
+            Self::Yaml => "yml",
+            Self::Json => "json",
+        }
+    }
+
+    pub fn parse(&self, contents: &str) -> Result<MukdukConfig> {
+        // Newly created config files are empty, which only toml considers a valid document.
+        if contents.trim().is_empty() {
+            return Ok(MukdukConfig::default());
+        }
+        Ok(match self {
+            Self::Toml => toml::from_str(contents)?,
+            Self::Yaml => serde_yaml::from_str(contents)?,
+            Self::Json => serde_json::from_str(contents)?,
+        })
+    }
+
+    pub fn serialize(&self, config: &MukdukConfig) -> Result<String> {
+        Ok(match self {
+            Self::Toml => toml::to_string_pretty(config)?,
+            Self::Yaml => serde_yaml::to_string(config)?,
+            Self::Json => serde_json::to_string_pretty(config)?,
+        })
+    }
 }
 
 /// # Use this for reading config from Environment Variables
@@ -34,9 +299,9 @@ impl MukdukConfig {
 /// ## Steps to add new Environment Variables:
 /// 1. Add the key name to this enum.
 /// 1. Add the new variant in the `as_str` impl
-///   (use the name of the env var you would like to provide).
+///    (use the name of the env var you would like to provide).
 /// 1. Implement the 'From' trait. You should implement this for the value
-///   that you would like the Env Var to be read as.
+///    that you would like the Env Var to be read as.
 ///
 /// ### Valid Examples
 /// This is what using an env variable for a boolean would look like.
@@ -71,6 +336,7 @@ impl MukdukConfig {
 /// env::set_var(ConfigEnvKey::DevMode.as_str(), "123not_bool");
 /// let is_dev_mode = bool::from(ConfigEnvKey::DevMode);
 /// ```
+#[derive(Debug, Clone, Copy)]
 pub enum ConfigEnvKey {
     Home,
     XDGConfig,
@@ -98,9 +364,6 @@ impl ConfigEnvKey {
     }
 }
 
-const DEFAULT_PANIC_MSG: &str =
-    "Check the impl block for the type you are trying to use and make sure the key is implemented.";
-
 /// This is what using an env variable for a String would look like.
 /// ```
 /// use std::env;
@@ -114,43 +377,147 @@ const DEFAULT_PANIC_MSG: &str =
 /// assert_eq!(otel_col_url, "tcp://localhost:4317");
 /// ```
 impl TryFrom<ConfigEnvKey> for PathBuf {
-    type Error = ConfigError;
-    fn try_from(env_key: ConfigEnvKey) -> Result<Self, ConfigError> {
+    type Error = MukdukError;
+    fn try_from(env_key: ConfigEnvKey) -> Result<Self, MukdukError> {
         match env_key {
-            ConfigEnvKey::Home => Ok(Self::from(
-                env::var(ConfigEnvKey::Home.as_str()).expect("HOME env var should be set"),
-            )),
-            ConfigEnvKey::XDGConfig => match env::var(ConfigEnvKey::XDGConfig.as_str()) {
-                Ok(config_dir) => Ok(Self::from(config_dir)),
-                Err(_err) => {
-                    let mut home = Self::try_from(ConfigEnvKey::Home)?;
-                    home.push(".config");
-                    log::trace!(
-                        "Error: error reading ${}. Using [{}]",
-                        ConfigEnvKey::XDGConfig.as_str(),
-                        home.as_os_str().to_string_lossy()
-                    );
-                    Ok(home)
-                }
-            },
-            ConfigEnvKey::XDGData => Ok(Self::from(
-                env::var(ConfigEnvKey::XDGData.as_str())
-                    .expect("XDG_DATA_HOME env var should be set"),
-            )),
-            ConfigEnvKey::XDGState => Ok(Self::from(
-                env::var(ConfigEnvKey::XDGState.as_str())
-                    .expect("XDG_STATE_HOME env var should be set"),
-            )),
-            #[allow(unreachable_patterns)]
-            // This is allowed because not all enum variants are guaranteed to be this type in the
-            // futrue.
-            _ => panic!("this key cannot be converted to PathBuf. {DEFAULT_PANIC_MSG}"),
+            ConfigEnvKey::Home => env::var(ConfigEnvKey::Home.as_str())
+                .map(Self::from)
+                .map_err(|_| MukdukError::MissingEnvVar(ConfigEnvKey::Home.as_str())),
+            ConfigEnvKey::XDGConfig => xdg_or_home(env_key, ".config"),
+            ConfigEnvKey::XDGData => xdg_or_home(env_key, ".local/share"),
+            ConfigEnvKey::XDGState => xdg_or_home(env_key, ".local/state"),
+        }
+    }
+}
+
+/// Read an XDG base directory, using the spec's default relative to $HOME when it is unset.
+fn xdg_or_home(env_key: ConfigEnvKey, home_relative: &str) -> Result<PathBuf, MukdukError> {
+    match env::var(env_key.as_str()) {
+        Ok(dir) => Ok(PathBuf::from(dir)),
+        Err(_err) => {
+            let mut home = PathBuf::try_from(ConfigEnvKey::Home)?;
+            home.push(home_relative);
+            log::trace!(
+                "Error: error reading ${}. Using [{}]",
+                env_key.as_str(),
+                home.as_os_str().to_string_lossy()
+            );
+            Ok(home)
         }
     }
 }
 
-#[derive(Error, Debug)]
-pub enum ConfigError {
-    #[error("genertic")]
-    Generic,
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use pretty_assertions::assert_eq;
+
+    use super::{ConfigFormat, MukdukConfig, ProjectSettings};
+    use crate::{error::MukdukError, multiplexer::tmux::TmuxSocket};
+
+    #[test]
+    fn should_pick_format_from_extension() {
+        assert_eq!(
+            ConfigFormat::from_path(&PathBuf::from("config.yml")).ok(),
+            Some(ConfigFormat::Yaml)
+        );
+        assert_eq!(
+            ConfigFormat::from_path(&PathBuf::from(".mukdukrc.json")).ok(),
+            Some(ConfigFormat::Json)
+        );
+        assert!(ConfigFormat::from_path(&PathBuf::from("config.ini")).is_err());
+    }
+
+    #[test]
+    fn should_parse_same_config_from_every_format() -> anyhow::Result<()> {
+        let toml = "[projects_dir]\ndefault = \"/projects\"\n";
+        let yaml = "projects_dir:\n  default: /projects\n";
+        let json = r#"{ "projects_dir": { "default": "/projects" } }"#;
+
+        for (format, contents) in [
+            (ConfigFormat::Toml, toml),
+            (ConfigFormat::Yaml, yaml),
+            (ConfigFormat::Json, json),
+        ] {
+            let config = format.parse(contents)?;
+            assert_eq!(
+                config.projects_dir.default,
+                Some(PathBuf::from("/projects"))
+            );
+            let round_trip = format.parse(&format.serialize(&config)?)?;
+            assert_eq!(round_trip.projects_dir.default, config.projects_dir.default);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn should_treat_empty_file_as_default_config() -> anyhow::Result<()> {
+        assert!(ConfigFormat::Yaml.parse("")?.projects_dir.default.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn should_detect_ambiguous_config_files() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        assert!(MukdukConfig::find_in(dir.path(), "config")?.is_none());
+
+        fs::write(dir.path().join("config.toml"), "")?;
+        assert_eq!(
+            MukdukConfig::find_in(dir.path(), "config")?,
+            Some(dir.path().join("config.toml"))
+        );
+
+        fs::write(dir.path().join("config.yml"), "")?;
+        assert!(matches!(
+            MukdukConfig::find_in(dir.path(), "config"),
+            Err(MukdukError::AmbiguousConfig(found)) if found.len() == 2
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn should_read_tmux_socket_from_either_key() -> anyhow::Result<()> {
+        let config = ConfigFormat::Yaml.parse("tmux:\n  socket_name: work\n")?;
+        assert_eq!(
+            config.tmux.socket()?,
+            Some(TmuxSocket::Name("work".to_string()))
+        );
+
+        let config =
+            ConfigFormat::Yaml.parse("tmux:\n  socket_name: work\n  socket_path: /tmp/pair\n")?;
+        assert!(matches!(
+            config.tmux.socket(),
+            Err(MukdukError::ConflictingConfigKeys(..))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn should_merge_project_tags_from_config_and_local_file() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let project = dir.path().join("mukduk");
+        fs::create_dir(&project)?;
+        fs::write(
+            project.join(ProjectSettings::LOCAL_FILE),
+            "tags = [\"rust\", \"cli\"]\nenv = \"auto\"\n[container]\nalways = true\n",
+        )?;
+
+        let config = ConfigFormat::Yaml.parse(&format!(
+            "projects:\n  mukduk:\n    tags: [work]\n  {}:\n    tags: [rust]\n",
+            project.to_string_lossy()
+        ))?;
+
+        assert_eq!(
+            config.project_settings("mukduk", &project).tags,
+            vec!["rust", "work", "cli"]
+        );
+        assert_eq!(config.project_settings("mukduk", &project).container, None);
+        assert_eq!(config.project_settings("mukduk", &project).env, None);
+        assert!(config
+            .project_settings("other", &dir.path().join("other"))
+            .tags
+            .is_empty());
+        Ok(())
+    }
 }
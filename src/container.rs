@@ -0,0 +1,172 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, path::Path};
+
+use crate::{error::MukdukError, shell};
+
+/// Compose files `docker compose` picks up on its own, in the order it prefers them.
+const COMPOSE_FILES: [&str; 4] = [
+    "compose.yaml",
+    "compose.yml",
+    "docker-compose.yaml",
+    "docker-compose.yml",
+];
+
+const DEVCONTAINER_FILE: &str = ".devcontainer/devcontainer.json";
+
+/// How a project's sessions use its container, set per project.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContainerSettings {
+    /// Open the project in its container even without `--container`.
+    #[serde(default)]
+    pub always: bool,
+    /// Compose service to exec into, only needed when there is more than one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service: Option<String>,
+    /// Shell to run inside of the container, defaults to `sh`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shell: Option<String>,
+}
+
+impl ContainerSettings {
+    /// Shell command for the project's panes that runs `shell` inside of its container.
+    pub fn session_command(&self, project_dir: &Path) -> Result<String> {
+        let container = Container::detect(project_dir, self)?;
+        Ok(container.session_command(self.shell.as_deref().unwrap_or("sh")))
+    }
+}
+
+/// The container a project is developed in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Container {
+    /// `.devcontainer/devcontainer.json`, run with the devcontainer cli.
+    Devcontainer,
+    /// A compose file, exec'ing into one of its services.
+    Compose { service: String },
+}
+
+#[derive(Deserialize)]
+struct ComposeFile {
+    #[serde(default)]
+    services: BTreeMap<String, serde_yaml::Value>,
+}
+
+impl Container {
+    /// Find the project's container, preferring a devcontainer over a compose file.
+    pub fn detect(project_dir: &Path, settings: &ContainerSettings) -> Result<Self> {
+        if project_dir.join(DEVCONTAINER_FILE).is_file() {
+            return Ok(Self::Devcontainer);
+        }
+        let Some(compose_file) = COMPOSE_FILES
+            .iter()
+            .map(|file| project_dir.join(file))
+            .find(|file| file.is_file())
+        else {
+            return Err(MukdukError::NoContainer(project_dir.to_path_buf()).into());
+        };
+        if let Some(service) = &settings.service {
+            return Ok(Self::Compose {
+                service: service.clone(),
+            });
+        }
+        let compose: ComposeFile = serde_yaml::from_str(&fs::read_to_string(&compose_file)?)?;
+        let services: Vec<String> = compose.services.into_keys().collect();
+        match services.as_slice() {
+            [service] => Ok(Self::Compose {
+                service: service.clone(),
+            }),
+            _ => Err(MukdukError::AmbiguousService {
+                file: compose_file,
+                services: services.join(", "),
+            }
+            .into()),
+        }
+    }
+
+    /// Shell command that starts the container when it is not running, then opens `shell` in it.
+    pub fn session_command(&self, shell: &str) -> String {
+        let shell = shell::quote(shell);
+        match self {
+            Self::Devcontainer => format!(
+                "devcontainer up --workspace-folder . >/dev/null && exec devcontainer exec --workspace-folder . {shell}"
+            ),
+            Self::Compose { service } => {
+                let service = shell::quote(service);
+                format!(
+                    "docker compose up -d {service} && exec docker compose exec {service} {shell}"
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use pretty_assertions::assert_eq;
+
+    use super::{Container, ContainerSettings};
+
+    #[test]
+    fn should_prefer_devcontainer() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        assert!(Container::detect(dir.path(), &ContainerSettings::default()).is_err());
+
+        fs::write(
+            dir.path().join("docker-compose.yml"),
+            "services:\n  app: {}\n",
+        )?;
+        fs::create_dir(dir.path().join(".devcontainer"))?;
+        fs::write(dir.path().join(".devcontainer/devcontainer.json"), "{}")?;
+        let container = Container::detect(dir.path(), &ContainerSettings::default())?;
+        assert_eq!(container, Container::Devcontainer);
+        assert_eq!(
+            container.session_command("bash"),
+            "devcontainer up --workspace-folder . >/dev/null && exec devcontainer exec --workspace-folder . bash"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn should_pick_compose_service() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::write(
+            dir.path().join("compose.yaml"),
+            "services:\n  app:\n    image: rust\n",
+        )?;
+        let container = Container::detect(dir.path(), &ContainerSettings::default())?;
+        assert_eq!(
+            container.session_command("sh"),
+            "docker compose up -d app && exec docker compose exec app sh"
+        );
+
+        fs::write(
+            dir.path().join("compose.yaml"),
+            "services:\n  app: {}\n  db: {}\n",
+        )?;
+        assert!(Container::detect(dir.path(), &ContainerSettings::default()).is_err());
+        let settings = ContainerSettings {
+            service: Some("db".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            Container::detect(dir.path(), &settings)?,
+            Container::Compose {
+                service: "db".to_string()
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn should_quote_service_and_shell() {
+        let container = Container::Compose {
+            service: "x; rm -rf ~; #".to_string(),
+        };
+        assert_eq!(
+            container.session_command("zsh"),
+            "docker compose up -d 'x; rm -rf ~; #' && exec docker compose exec 'x; rm -rf ~; #' zsh"
+        );
+    }
+}
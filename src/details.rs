@@ -0,0 +1,300 @@
+use clap::ValueEnum;
+use serde::Serialize;
+use std::{
+    fs,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    cli::MukdukContext,
+    git::{Git, GitStatus},
+    multiplexer::{BackendOptions, Multiplexers, SanitizePolicy},
+    output::{human_age, human_bytes, Tabular},
+    project::Project,
+};
+
+/// Extra information `project list --with` can collect for each project.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Detail {
+    /// Current branch, dirty flag, and ahead/behind counts.
+    Git,
+    /// Languages and build systems detected from marker files.
+    Lang,
+    /// Disk usage and last modified time.
+    Size,
+    /// Multiplexers with a live session for the project.
+    Sessions,
+}
+
+/// Marker file, language, build system.
+const MARKERS: [(&str, &str, &str); 14] = [
+    ("Cargo.toml", "rust", "cargo"),
+    ("go.mod", "go", "go"),
+    ("package.json", "javascript", "npm"),
+    ("deno.json", "typescript", "deno"),
+    ("pyproject.toml", "python", "pyproject"),
+    ("requirements.txt", "python", "pip"),
+    ("Gemfile", "ruby", "bundler"),
+    ("pom.xml", "java", "maven"),
+    ("build.gradle", "java", "gradle"),
+    ("build.gradle.kts", "kotlin", "gradle"),
+    ("mix.exs", "elixir", "mix"),
+    ("CMakeLists.txt", "c/c++", "cmake"),
+    ("flake.nix", "nix", "nix"),
+    ("Makefile", "make", "make"),
+];
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Lang {
+    pub languages: Vec<&'static str>,
+    pub build_systems: Vec<&'static str>,
+}
+
+impl Lang {
+    pub fn detect(project_dir: &Path) -> Self {
+        let mut lang = Self::default();
+        for (marker, language, build_system) in MARKERS {
+            if !project_dir.join(marker).is_file() {
+                continue;
+            }
+            if !lang.languages.contains(&language) {
+                lang.languages.push(language);
+            }
+            if !lang.build_systems.contains(&build_system) {
+                lang.build_systems.push(build_system);
+            }
+        }
+        lang
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct DiskUsage {
+    pub bytes: u64,
+    /// Unix timestamp of the most recently modified file, ignoring `.git`.
+    pub modified: Option<u64>,
+}
+
+impl DiskUsage {
+    /// Walk the project without following symlinks, skipping anything that can not be read.
+    pub fn measure(project_dir: &Path) -> Self {
+        let mut usage = Self::default();
+        usage.walk(project_dir, false);
+        usage
+    }
+
+    fn walk(&mut self, dir: &Path, in_git_dir: bool) {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            if metadata.is_dir() {
+                self.walk(&entry.path(), in_git_dir || entry.file_name() == ".git");
+                continue;
+            }
+            self.bytes += metadata.len();
+            if in_git_dir {
+                continue;
+            }
+            let modified = metadata
+                .modified()
+                .ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|since_epoch| since_epoch.as_secs());
+            self.modified = self.modified.max(modified);
+        }
+    }
+}
+
+/// Sessions that are currently running, looked up once for every multiplexer.
+#[derive(Default)]
+pub struct LiveSessions(Vec<(Multiplexers, SanitizePolicy, Vec<String>)>);
+
+impl LiveSessions {
+    /// Multiplexers that are not installed or not running are treated as having no sessions.
+    pub fn collect(context: &MukdukContext) -> Self {
+        Self::new(
+            Multiplexers::value_variants()
+                .iter()
+                .map(|&multiplexer| {
+                    let backend = multiplexer.backend(BackendOptions {
+                        tmux_socket: context.tmux_socket.clone(),
+                        ..Default::default()
+                    });
+                    let sessions = backend.get_sessions().unwrap_or_default();
+                    (
+                        multiplexer,
+                        context.config.sanitize_policy(multiplexer),
+                        sessions,
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    pub const fn new(sessions: Vec<(Multiplexers, SanitizePolicy, Vec<String>)>) -> Self {
+        Self(sessions)
+    }
+
+    /// Drop the sessions of every other multiplexer.
+    pub fn only(self, multiplexer: Multiplexers) -> Self {
+        Self(
+            self.0
+                .into_iter()
+                .filter(|(candidate, ..)| *candidate == multiplexer)
+                .collect(),
+        )
+    }
+
+    /// Every session name along with its multiplexer and sanitize policy.
+    pub fn sessions(&self) -> impl Iterator<Item = (Multiplexers, &SanitizePolicy, &str)> {
+        self.0.iter().flat_map(|(multiplexer, sanitize, sessions)| {
+            sessions
+                .iter()
+                .map(move |session| (*multiplexer, sanitize, session.as_str()))
+        })
+    }
+
+    pub fn for_project(&self, project: &Project) -> Vec<Multiplexers> {
+        self.0
+            .iter()
+            .filter(|(_, sanitize, sessions)| sessions.contains(&sanitize.apply(&project.name)))
+            .map(|(multiplexer, ..)| *multiplexer)
+            .collect()
+    }
+}
+
+/// A project along with whatever was asked for with `--with`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectDetails {
+    #[serde(flatten)]
+    pub project: Project,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub git: Option<GitStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lang: Option<Lang>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<DiskUsage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sessions: Option<Vec<Multiplexers>>,
+    #[serde(skip)]
+    with: Vec<Detail>,
+}
+
+impl ProjectDetails {
+    pub fn collect(project: Project, with: &[Detail], git: &Git, live: &LiveSessions) -> Self {
+        let wants = |detail| with.contains(&detail);
+        Self {
+            git: wants(Detail::Git)
+                .then(|| {
+                    git.status(&project.path).unwrap_or_else(|err| {
+                        log::warn!("skipping git status of {}: {err}", project.name);
+                        None
+                    })
+                })
+                .flatten(),
+            lang: wants(Detail::Lang).then(|| Lang::detect(&project.path)),
+            size: wants(Detail::Size).then(|| DiskUsage::measure(&project.path)),
+            sessions: wants(Detail::Sessions).then(|| live.for_project(&project)),
+            with: with.to_vec(),
+            project,
+        }
+    }
+}
+
+impl Tabular for ProjectDetails {
+    fn headers(&self) -> Vec<&'static str> {
+        let mut headers = vec!["name", "path", "tags"];
+        for detail in &self.with {
+            match detail {
+                Detail::Git => headers.push("git"),
+                Detail::Lang => headers.push("lang"),
+                Detail::Size => headers.extend(["size", "modified"]),
+                Detail::Sessions => headers.push("sessions"),
+            }
+        }
+        headers
+    }
+
+    fn row(&self) -> Vec<String> {
+        let mut row = vec![
+            self.project.name.clone(),
+            self.project.path.to_string_lossy().to_string(),
+            self.project.tags.join(","),
+        ];
+        for detail in &self.with {
+            match detail {
+                Detail::Git => row.push(
+                    self.git
+                        .as_ref()
+                        .map_or_else(|| "-".to_string(), GitStatus::summary),
+                ),
+                Detail::Lang => row.push(
+                    self.lang
+                        .as_ref()
+                        .map_or_else(String::new, |lang| lang.languages.join(",")),
+                ),
+                Detail::Size => {
+                    let size = self.size.clone().unwrap_or_default();
+                    let now = SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .map_or(0, |since_epoch| since_epoch.as_secs());
+                    row.push(human_bytes(size.bytes));
+                    row.push(
+                        size.modified
+                            .map_or_else(|| "-".to_string(), |modified| human_age(now, modified)),
+                    );
+                }
+                Detail::Sessions => row.push(
+                    self.sessions
+                        .iter()
+                        .flatten()
+                        .map(|m| format!("{m:?}").to_lowercase())
+                        .collect::<Vec<_>>()
+                        .join(","),
+                ),
+            }
+        }
+        row
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use pretty_assertions::assert_eq;
+
+    use super::{DiskUsage, Lang};
+
+    #[test]
+    fn should_detect_languages_and_build_systems() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        assert_eq!(Lang::detect(dir.path()), Lang::default());
+
+        fs::write(dir.path().join("Cargo.toml"), "")?;
+        fs::write(dir.path().join("Makefile"), "")?;
+        let lang = Lang::detect(dir.path());
+        assert_eq!(lang.languages, vec!["rust", "make"]);
+        assert_eq!(lang.build_systems, vec!["cargo", "make"]);
+        Ok(())
+    }
+
+    #[test]
+    fn should_measure_files_recursively() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::create_dir_all(dir.path().join("src"))?;
+        fs::create_dir_all(dir.path().join(".git"))?;
+        fs::write(dir.path().join("src").join("main.rs"), "fn main() {}")?;
+        fs::write(dir.path().join(".git").join("HEAD"), "ref")?;
+
+        let usage = DiskUsage::measure(dir.path());
+        assert_eq!(usage.bytes, 15);
+        assert!(usage.modified.is_some());
+        Ok(())
+    }
+}
@@ -0,0 +1,69 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// How a project's shells load its toolchain, set per project.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Environment {
+    /// Whichever of the below the project has files for, preferring direnv.
+    Auto,
+    /// `.envrc`, loaded with `direnv exec`.
+    Direnv,
+    /// `flake.nix` with `nix develop`, or else `shell.nix` with `nix-shell`.
+    Nix,
+}
+
+impl Environment {
+    const ENVRC: &'static str = ".envrc";
+    const FLAKE: &'static str = "flake.nix";
+    const SHELL_NIX: &'static str = "shell.nix";
+
+    /// Shell command that starts `$SHELL` with the project's environment loaded, `None` when the
+    /// project has no file for it.
+    pub fn session_command(self, project_dir: &Path) -> Option<String> {
+        let has = |file: &str| project_dir.join(file).is_file();
+        match self {
+            Self::Auto => Self::Direnv
+                .session_command(project_dir)
+                .or_else(|| Self::Nix.session_command(project_dir)),
+            Self::Direnv if has(Self::ENVRC) => Some("direnv exec . $SHELL".to_string()),
+            Self::Nix if has(Self::FLAKE) => Some("nix develop -c $SHELL".to_string()),
+            Self::Nix if has(Self::SHELL_NIX) => Some("nix-shell --run $SHELL".to_string()),
+            Self::Direnv | Self::Nix => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use pretty_assertions::assert_eq;
+
+    use super::Environment;
+
+    #[test]
+    fn should_use_files_the_project_has() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        assert_eq!(Environment::Auto.session_command(dir.path()), None);
+
+        fs::write(dir.path().join("shell.nix"), "")?;
+        assert_eq!(
+            Environment::Auto.session_command(dir.path()),
+            Some("nix-shell --run $SHELL".to_string())
+        );
+        fs::write(dir.path().join("flake.nix"), "")?;
+        assert_eq!(
+            Environment::Nix.session_command(dir.path()),
+            Some("nix develop -c $SHELL".to_string())
+        );
+        assert_eq!(Environment::Direnv.session_command(dir.path()), None);
+
+        fs::write(dir.path().join(".envrc"), "use flake")?;
+        assert_eq!(
+            Environment::Auto.session_command(dir.path()),
+            Some("direnv exec . $SHELL".to_string())
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,288 @@
+use std::{path::PathBuf, process::Command};
+
+use colored::Colorize;
+use thiserror::Error;
+
+/// Exit codes loosely follow `sysexits.h` so scripts wrapping mukduk can tell failures apart.
+pub mod exit_code {
+    pub const GENERAL: i32 = 1;
+    pub const USAGE: i32 = 64;
+    pub const UNAVAILABLE: i32 = 69;
+    pub const CONFIG: i32 = 78;
+    pub const NOT_FOUND: i32 = 127;
+    pub const CANCELLED: i32 = 130;
+}
+
+#[derive(Error, Debug)]
+pub enum MukdukError {
+    #[error("`{0}` could not be found on your $PATH")]
+    MissingBinary(String),
+
+    #[error("no value was provided for `{0}`")]
+    MissingConfigKey(&'static str),
+
+    #[error("the ${0} environment variable is not set")]
+    MissingEnvVar(&'static str),
+
+    #[error("only one of `{0}` and `{1}` can be set")]
+    ConflictingConfigKeys(&'static str, &'static str),
+
+    #[error("config file does not exist: {0:?}")]
+    ConfigNotFound(PathBuf),
+
+    #[error("found multiple config files: {0:?}")]
+    AmbiguousConfig(Vec<PathBuf>),
+
+    #[error("unsupported config file extension: {0:?}")]
+    UnsupportedConfigFormat(PathBuf),
+
+    #[error("no {0} was selected")]
+    NoSelection(&'static str),
+
+    #[error("no command was provided")]
+    NoCommand,
+
+    #[error("no project named `{0}` was found")]
+    ProjectNotFound(String),
+
+    #[error("no live session named `{0}` was found")]
+    SessionNotFound(String),
+
+    #[error("no devcontainer or compose file was found in {0:?}")]
+    NoContainer(PathBuf),
+
+    #[error("{file:?} has several services to choose from: {services}")]
+    AmbiguousService { file: PathBuf, services: String },
+
+    #[error("no template named `{name}` is configured")]
+    UnknownProjectTemplate { name: String, available: String },
+
+    #[error("{0:?} already exists")]
+    AlreadyExists(PathBuf),
+
+    #[error("`{name}` has {problems}")]
+    UnsavedWork { name: String, problems: String },
+
+    #[error("project path does not have a usable directory name: {0:?}")]
+    InvalidProjectPath(PathBuf),
+
+    #[error("`{command}` failed{}", if stderr.is_empty() { String::new() } else { format!(": {stderr}") })]
+    CommandFailed { command: String, stderr: String },
+
+    #[error("multiple projects would share the `{session}` session: {paths:?}")]
+    SessionCollision {
+        session: String,
+        paths: Vec<PathBuf>,
+    },
+
+    #[error("`{{{field}}}` is not a column that can be used in a template")]
+    UnknownTemplateField { field: String, available: String },
+
+    #[error("could not kill {failed} of {total} sessions: {sessions}")]
+    KillFailed {
+        failed: usize,
+        total: usize,
+        sessions: String,
+    },
+
+    #[error("git failed in {failed} of {total} projects")]
+    GitFailed { failed: usize, total: usize },
+
+    #[error("{0} cannot switch sessions while inside an active session")]
+    NestedSession(&'static str),
+
+    #[error("mukduk has not switched sessions yet")]
+    NoPreviousSession,
+
+    #[error("no multiplexer was given to open `{0}` with")]
+    NoMultiplexer(String),
+
+    #[error("{multiplexer} does not support {operation}")]
+    Unsupported {
+        multiplexer: &'static str,
+        operation: &'static str,
+    },
+}
+
+impl MukdukError {
+    /// Build a [`MukdukError::CommandFailed`] from a command that exited unsuccessfully.
+    pub fn command_failed(command: &Command, stderr: &[u8]) -> Self {
+        Self::CommandFailed {
+            command: display_command(command),
+            stderr: String::from_utf8_lossy(stderr).trim().to_string(),
+        }
+    }
+
+    pub const fn exit_code(&self) -> i32 {
+        match self {
+            Self::MissingBinary(_) => exit_code::NOT_FOUND,
+            Self::MissingConfigKey(_)
+            | Self::MissingEnvVar(_)
+            | Self::ConflictingConfigKeys(..)
+            | Self::ConfigNotFound(_)
+            | Self::AmbiguousConfig(_)
+            | Self::UnsupportedConfigFormat(_)
+            | Self::SessionCollision { .. }
+            | Self::AmbiguousService { .. }
+            | Self::UnknownProjectTemplate { .. } => exit_code::CONFIG,
+            Self::NoSelection(_) => exit_code::CANCELLED,
+            Self::NoPreviousSession => exit_code::GENERAL,
+            Self::NoCommand
+            | Self::NestedSession(_)
+            | Self::NoMultiplexer(_)
+            | Self::NoContainer(_)
+            | Self::Unsupported { .. }
+            | Self::UnknownTemplateField { .. } => exit_code::USAGE,
+            Self::CommandFailed { .. } | Self::GitFailed { .. } | Self::KillFailed { .. } => {
+                exit_code::UNAVAILABLE
+            }
+            Self::InvalidProjectPath(_)
+            | Self::ProjectNotFound(_)
+            | Self::SessionNotFound(_)
+            | Self::AlreadyExists(_)
+            | Self::UnsavedWork { .. } => exit_code::GENERAL,
+        }
+    }
+
+    /// Suggestion shown to the user below the error message.
+    pub fn hint(&self) -> Option<String> {
+        match self {
+            Self::MissingBinary(binary) => Some(format!(
+                "install `{binary}` and make sure it is on your $PATH, see the README for links."
+            )),
+            Self::MissingConfigKey("projects_dir") => Some(
+                "pass `--projects-dir`, set $PROJECTS_DIR, or set `projects_dir.default` in your config file."
+                    .to_string(),
+            ),
+            Self::MissingConfigKey("archive_dir") => Some(
+                "set `archive_dir` in your config file, or pass `--delete`.".to_string(),
+            ),
+            Self::ProjectNotFound(_) => {
+                Some("check the name with `mukduk project list`.".to_string())
+            }
+            Self::SessionNotFound(_) => {
+                Some("check the name with `mukduk session list`.".to_string())
+            }
+            Self::NoContainer(_) => Some(
+                "add a `.devcontainer/devcontainer.json` or `docker-compose.yml`, or open it without `--container`."
+                    .to_string(),
+            ),
+            Self::AmbiguousService { .. } => Some(
+                "set `container.service` for the project under `projects` in your config file."
+                    .to_string(),
+            ),
+            Self::UnknownProjectTemplate { available, .. } if available.is_empty() => {
+                Some("add one under `templates` in your config file.".to_string())
+            }
+            Self::UnknownProjectTemplate { available, .. } => {
+                Some(format!("configured templates are: {available}."))
+            }
+            Self::AlreadyExists(_) => Some("move or rename it, and try again.".to_string()),
+            Self::InvalidProjectPath(_) => {
+                Some("project names can not be empty, `.`, `..`, or contain `/`.".to_string())
+            }
+            Self::UnsavedWork { .. } => {
+                Some("commit and push it first, or pass `--force`.".to_string())
+            }
+            Self::MissingEnvVar(key) => Some(format!("export ${key} and try again.")),
+            Self::ConflictingConfigKeys(..) => {
+                Some("remove one of them from your config file.".to_string())
+            }
+            Self::ConfigNotFound(_) => {
+                Some("check the path passed to `--config-path`.".to_string())
+            }
+            Self::AmbiguousConfig(_) => Some(
+                "remove all but one of them, or pick one with `--config-path`.".to_string(),
+            ),
+            Self::UnsupportedConfigFormat(_) => {
+                Some("config files must end in .toml, .yaml, .yml, or .json.".to_string())
+            }
+            Self::SessionCollision { .. } => Some(
+                "give one of them a different `alias` under `projects` in your config file, or pass `-n`."
+                    .to_string(),
+            ),
+            Self::UnknownTemplateField { available, .. } => {
+                Some(format!("available columns are: {available}."))
+            }
+            Self::NoCommand => Some("to see commands use `--help`.".to_string()),
+            Self::NestedSession(_) => {
+                Some("try detaching from your current session, and try again.".to_string())
+            }
+            Self::NoPreviousSession => {
+                Some("switch with `mukduk switch` or `mukduk project open` first.".to_string())
+            }
+            Self::NoMultiplexer(_) => {
+                Some("pass `-m`, or run it from inside of tmux or zellij.".to_string())
+            }
+            Self::Unsupported { .. } => Some("try again with `-m tmux`.".to_string()),
+            Self::MissingConfigKey(_)
+            | Self::NoSelection(_)
+            | Self::CommandFailed { .. }
+            | Self::GitFailed { .. }
+            | Self::KillFailed { .. } => None,
+        }
+    }
+}
+
+/// Print an error (and hint when there is one) for the user, returning the exit code to use.
+pub fn report(err: &anyhow::Error) -> i32 {
+    match err.downcast_ref::<MukdukError>() {
+        Some(MukdukError::NoSelection(what)) => {
+            eprintln!("{}", format!("No {what} was selected.").yellow().bold());
+            exit_code::CANCELLED
+        }
+        Some(mukduk_err) => {
+            eprintln!("\n{} {mukduk_err}", "error:".red().bold());
+            if let Some(hint) = mukduk_err.hint() {
+                eprintln!("{} {hint}", "hint:".yellow().bold());
+            }
+            eprintln!();
+            mukduk_err.exit_code()
+        }
+        None => {
+            eprintln!("\n{} {err:#}\n", "error:".red().bold());
+            exit_code::GENERAL
+        }
+    }
+}
+
+/// Render a command the way it would be typed into a shell, for error messages and logs.
+pub fn display_command(command: &Command) -> String {
+    std::iter::once(command.get_program())
+        .chain(command.get_args())
+        .map(|part| part.to_string_lossy().to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+
+    use pretty_assertions::assert_eq;
+
+    use super::{display_command, exit_code, MukdukError};
+
+    #[test]
+    fn should_render_failed_command_with_stderr() {
+        let mut command = Command::new("tmux");
+        command.args(["has-session", "-t", "=muk"]);
+        let err = MukdukError::command_failed(&command, b"can't find session: muk\n");
+
+        assert_eq!(display_command(&command), "tmux has-session -t =muk");
+        assert_eq!(
+            err.to_string(),
+            "`tmux has-session -t =muk` failed: can't find session: muk"
+        );
+        assert_eq!(err.exit_code(), exit_code::UNAVAILABLE);
+    }
+
+    #[test]
+    fn should_hint_how_to_set_missing_projects_dir() {
+        let err = MukdukError::MissingConfigKey("projects_dir");
+        assert_eq!(err.exit_code(), exit_code::CONFIG);
+        assert!(err
+            .hint()
+            .is_some_and(|hint| hint.contains("--projects-dir")));
+    }
+}
@@ -1,19 +1,70 @@
 use anyhow::Result;
-use std::{
-    ffi::OsStr,
-    fmt::Display,
-    io::Write,
-    process::{Command, Stdio},
+use std::{env, ffi::OsStr, fmt::Display, fs, process::Command};
+
+use crate::{
+    config::PickerConfig,
+    runner::{CommandRunner, SystemRunner},
+    shell,
 };
 
-pub struct FzfCmd {
+/// First fzf release with `--tmux`.
+const TMUX_FLAG_VERSION: (u32, u32) = (0, 53);
+
+pub struct FzfCmd<'a> {
     command: Command,
+    runner: &'a dyn CommandRunner,
+    /// Size of the `tmux display-popup` to run fzf in, for versions of fzf without `--tmux`.
+    display_popup: Option<String>,
 }
 
-impl FzfCmd {
+impl FzfCmd<'static> {
     pub fn new() -> Self {
+        Self::with_runner(&SystemRunner)
+    }
+}
+
+impl<'a> FzfCmd<'a> {
+    pub fn with_runner(runner: &'a dyn CommandRunner) -> Self {
         Self {
             command: Command::new("fzf"),
+            runner,
+            display_popup: None,
+        }
+    }
+
+    /// Show the picker in a tmux popup when `picker` asks for one. Outside of tmux the picker
+    /// falls back to the current terminal.
+    pub fn picker(&mut self, picker: &PickerConfig) -> &mut Self {
+        if let Some(size) = picker.popup() {
+            self.popup(size, env::var_os("TMUX").is_some());
+        }
+        self
+    }
+
+    /// `--tmux` needs fzf 0.53 or newer, older versions are run inside of `tmux display-popup`.
+    fn popup(&mut self, size: &str, in_tmux: bool) {
+        if self.supports_tmux_flag() {
+            self.command.arg(format!("--tmux={size}"));
+        } else if in_tmux {
+            self.display_popup = Some(size.to_string());
+        }
+    }
+
+    fn supports_tmux_flag(&self) -> bool {
+        let Ok(output) = self.runner.run(Command::new("fzf").arg("--version")) else {
+            return false;
+        };
+        // Like `0.53.0 (c4a9ccd)`.
+        let version = String::from_utf8_lossy(&output.stdout);
+        let mut numbers = version
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .split('.')
+            .map(|part| part.parse::<u32>().ok());
+        match (numbers.next().flatten(), numbers.next().flatten()) {
+            (Some(major), Some(minor)) => (major, minor) >= TMUX_FLAG_VERSION,
+            _ => false,
         }
     }
 
@@ -45,25 +96,10 @@ impl FzfCmd {
     }
 
     pub fn find_string(&mut self, input: &str) -> Result<String> {
-        let mut fzf_child = self
-            .command
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .spawn()
-            .expect("fzf command should spawn");
-
-        // Get the stdin handle of the child process
-        if let Some(stdin) = &mut fzf_child.stdin {
-            // Write your input string to the command's stdin
-            stdin
-                .write_all(input.as_bytes())
-                .expect("should be able to pass project names to fzf stdin");
-        } else {
-            eprintln!("Failed to get stdin handle for the child process");
+        if let Some(size) = &self.display_popup {
+            return self.find_in_display_popup(size, input);
         }
-
-        // Ensure the child process has finished
-        let output = fzf_child.wait_with_output()?;
+        let output = self.runner.run_with_input(&mut self.command, input)?;
 
         if output.status.success() {
             return Ok(String::from_utf8_lossy(&output.stdout).trim().to_string());
@@ -71,4 +107,143 @@ impl FzfCmd {
 
         Ok("".to_string())
     }
+
+    /// A popup has no stdin or stdout to pipe through, so the choices and the selection are
+    /// passed in files instead.
+    fn find_in_display_popup(&self, size: &str, input: &str) -> Result<String> {
+        // Only readable by the user, and removed again when dropped.
+        let dir = tempfile::Builder::new().prefix("mukduk-fzf-").tempdir()?;
+        let (input_file, output_file) = (dir.path().join("input"), dir.path().join("output"));
+        fs::write(&input_file, input)?;
+
+        let fzf = std::iter::once(self.command.get_program())
+            .chain(self.command.get_args())
+            .map(|arg| shell::quote(&arg.to_string_lossy()))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let (width, height) = popup_dimensions(size);
+        let result = self.runner.run(
+            Command::new("tmux")
+                .args(["display-popup", "-E", "-w", &width, "-h", &height])
+                .arg(format!(
+                    "{fzf} < {} > {}",
+                    shell::quote(&input_file.to_string_lossy()),
+                    shell::quote(&output_file.to_string_lossy())
+                )),
+        );
+        let picked = fs::read_to_string(&output_file).unwrap_or_default();
+        if let Err(err) = dir.close() {
+            log::debug!("could not remove the fzf popup files: {err}");
+        }
+        result?;
+        Ok(picked.trim().to_string())
+    }
+}
+
+/// Width and height of a popup from an `fzf --tmux` size like `center,80%,60%` or `bottom,40%`.
+/// `display-popup` always centers the popup, so the position only decides which size is which.
+fn popup_dimensions(size: &str) -> (String, String) {
+    const FULL: &str = "100%";
+    const HALF: &str = "50%";
+    let mut parts: Vec<&str> = size.split(',').collect();
+    let position = match parts.first() {
+        Some(first) if first.starts_with(|c: char| c.is_ascii_alphabetic()) => parts.remove(0),
+        _ => "center",
+    };
+    let first = parts.first().copied().unwrap_or(HALF);
+    let (width, height) = match position {
+        "top" | "bottom" => (FULL, first),
+        "left" | "right" => (first, FULL),
+        _ => (first, parts.get(1).copied().unwrap_or(first)),
+    };
+    (width.to_string(), height.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::{popup_dimensions, FzfCmd};
+    use crate::{config::PickerConfig, runner::fake::FakeRunner};
+
+    #[test]
+    fn should_pipe_choices_and_return_selection() -> anyhow::Result<()> {
+        let runner = FakeRunner::new().on("fzf --multi", 0, "mukduk\n", "");
+        let picked = FzfCmd::with_runner(&runner)
+            .args(["--multi"])
+            .find_vec(vec!["mukduk", "scratch"])?;
+
+        assert_eq!(picked, "mukduk");
+        assert_eq!(runner.calls()[0].input.as_deref(), Some("mukduk\nscratch"));
+        Ok(())
+    }
+
+    #[test]
+    fn should_only_use_popup_when_configured() -> anyhow::Result<()> {
+        let runner = FakeRunner::new().on("fzf --version", 0, "0.54.0 (brew)\n", "");
+        let mut picker = PickerConfig::default();
+        FzfCmd::with_runner(&runner)
+            .picker(&picker)
+            .find_vec(vec!["mukduk"])?;
+        picker.popup = true;
+        FzfCmd::with_runner(&runner)
+            .picker(&picker)
+            .find_vec(vec!["mukduk"])?;
+        picker.popup_size = Some("bottom,40%".to_string());
+        FzfCmd::with_runner(&runner)
+            .picker(&picker)
+            .find_vec(vec!["mukduk"])?;
+
+        let commands: Vec<_> = runner
+            .commands()
+            .into_iter()
+            .filter(|command| command != "fzf --version")
+            .collect();
+        assert_eq!(
+            commands,
+            vec!["fzf", "fzf --tmux=center,80%,60%", "fzf --tmux=bottom,40%"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn should_fall_back_to_display_popup_for_old_fzf() -> anyhow::Result<()> {
+        let runner = FakeRunner::new().on("fzf --version", 0, "0.44.1 (debian)\n", "");
+        let mut fzf = FzfCmd::with_runner(&runner);
+        fzf.args(["--delimiter", "\t"]).popup("bottom,40%", true);
+        assert_eq!(fzf.find_vec(vec!["mukduk"])?, "");
+
+        let commands = runner.commands();
+        assert_eq!(commands[0], "fzf --version");
+        assert!(
+            commands[1].starts_with("tmux display-popup -E -w 100% -h 40% fzf --delimiter '\t' < ")
+                && commands[1].contains("/mukduk-fzf-"),
+            "{}",
+            commands[1]
+        );
+
+        let runner = FakeRunner::new().on("fzf --version", 0, "0.44.1 (debian)\n", "");
+        FzfCmd::with_runner(&runner).popup("center,80%,60%", false);
+        assert_eq!(runner.commands(), vec!["fzf --version"]);
+        Ok(())
+    }
+
+    #[test]
+    fn should_size_popups_like_fzf() {
+        let dims = |size| {
+            let (width, height) = popup_dimensions(size);
+            format!("{width}x{height}")
+        };
+        assert_eq!(dims("center,80%,60%"), "80%x60%");
+        assert_eq!(dims("70%"), "70%x70%");
+        assert_eq!(dims("left,30%"), "30%x100%");
+        assert_eq!(dims("top"), "100%x50%");
+    }
+
+    #[test]
+    fn should_return_empty_selection_when_cancelled() -> anyhow::Result<()> {
+        let runner = FakeRunner::new().on("fzf", 130, "", "");
+        assert_eq!(FzfCmd::with_runner(&runner).find_vec(vec!["mukduk"])?, "");
+        Ok(())
+    }
 }
@@ -0,0 +1,288 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use colored::Colorize;
+use serde::Serialize;
+use std::{env, path::Path, process::Command};
+
+use crate::{error::MukdukError, output::Tabular, project::Project, runner::CommandRunner};
+
+/// Runs git against project repos through a [`CommandRunner`].
+pub struct Git<'a> {
+    runner: &'a dyn CommandRunner,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct GitStatus {
+    /// Checked out branch, `None` when the head is detached.
+    pub branch: Option<String>,
+    /// Whether there are staged, unstaged, or untracked changes.
+    pub dirty: bool,
+    /// Commits not on the upstream branch, `None` without an upstream.
+    pub ahead: Option<u32>,
+    /// Commits on the upstream branch that are not checked out, `None` without an upstream.
+    pub behind: Option<u32>,
+}
+
+/// Operations that `project git` runs across projects.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GitOp {
+    /// Only read the status.
+    Status,
+    /// Fetch every remote.
+    Fetch,
+    /// Fast-forward the checked out branch from its upstream.
+    Pull,
+    /// Fetch every remote, dropping remote branches that no longer exist.
+    Prune,
+}
+
+impl<'a> Git<'a> {
+    pub fn new(runner: &'a dyn CommandRunner) -> Self {
+        Self { runner }
+    }
+
+    fn git(repo: &Path) -> Command {
+        let mut command = Command::new("git");
+        // Several repos can be worked on at once, so neither git nor ssh may wait on a prompt.
+        command.arg("-C").arg(repo).env("GIT_TERMINAL_PROMPT", "0");
+        if env::var_os("GIT_SSH_COMMAND").is_none() {
+            command.env("GIT_SSH_COMMAND", "ssh -o BatchMode=yes");
+        }
+        command
+    }
+
+    pub fn is_repo(repo: &Path) -> bool {
+        repo.join(".git").exists()
+    }
+
+    /// Status of the repo at `repo`, `None` when it is not a git repo.
+    pub fn status(&self, repo: &Path) -> Result<Option<GitStatus>> {
+        if !Self::is_repo(repo) {
+            return Ok(None);
+        }
+        let output = self.runner.run_checked(Self::git(repo).args([
+            "status",
+            "--porcelain=v2",
+            "--branch",
+        ]))?;
+        Ok(Some(GitStatus::parse(&String::from_utf8_lossy(
+            &output.stdout,
+        ))))
+    }
+
+    /// Create an empty repo in the existing directory `repo`.
+    pub fn init(&self, repo: &Path) -> Result<()> {
+        self.runner
+            .run_checked(Self::git(repo).args(["init", "-q"]))?;
+        Ok(())
+    }
+
+    /// Run `op`, returning the status of the repo afterwards.
+    pub fn run(&self, op: GitOp, repo: &Path) -> Result<Option<GitStatus>> {
+        let args: &[&str] = match op {
+            GitOp::Status => &[],
+            GitOp::Fetch => &["fetch", "--all"],
+            GitOp::Pull => &["pull", "--ff-only"],
+            GitOp::Prune => &["fetch", "--all", "--prune"],
+        };
+        if !args.is_empty() && Self::is_repo(repo) {
+            self.runner.run_checked(Self::git(repo).args(args))?;
+        }
+        self.status(repo)
+    }
+}
+
+impl GitStatus {
+    /// Work that would be lost along with the repo, like `uncommitted changes`.
+    pub fn unsaved_work(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.dirty {
+            problems.push("uncommitted changes".to_string());
+        }
+        if let Some(ahead) = self.ahead.filter(|ahead| *ahead > 0) {
+            problems.push(format!("{ahead} unpushed commit(s)"));
+        }
+        problems
+    }
+
+    /// Short form like `main* +2 -1`, where `*` means dirty.
+    pub fn summary(&self) -> String {
+        let mut summary = self
+            .branch
+            .clone()
+            .unwrap_or_else(|| "(detached)".to_string());
+        if self.dirty {
+            summary.push('*');
+        }
+        match (self.ahead, self.behind) {
+            (Some(0), Some(0)) | (None, None) => {}
+            (ahead, behind) => summary.push_str(&format!(
+                " +{} -{}",
+                ahead.unwrap_or(0),
+                behind.unwrap_or(0)
+            )),
+        }
+        summary
+    }
+
+    /// Parse the output of `git status --porcelain=v2 --branch`.
+    pub fn parse(porcelain: &str) -> Self {
+        let mut status = Self::default();
+        for line in porcelain.lines() {
+            if let Some(head) = line.strip_prefix("# branch.head ") {
+                status.branch = (head != "(detached)").then(|| head.to_string());
+            } else if let Some(counts) = line.strip_prefix("# branch.ab ") {
+                for count in counts.split_whitespace() {
+                    if let Some(ahead) = count.strip_prefix('+') {
+                        status.ahead = ahead.parse().ok();
+                    } else if let Some(behind) = count.strip_prefix('-') {
+                        status.behind = behind.parse().ok();
+                    }
+                }
+            } else if !line.starts_with('#') && !line.is_empty() {
+                status.dirty = true;
+            }
+        }
+        status
+    }
+}
+
+/// Outcome of running a [`GitOp`] in one project.
+#[derive(Debug, Clone, Serialize)]
+pub struct GitReport {
+    #[serde(flatten)]
+    pub project: Project,
+    pub status: Option<GitStatus>,
+    pub error: Option<String>,
+}
+
+impl GitReport {
+    pub fn new(project: Project, result: Result<Option<GitStatus>>) -> Self {
+        let (status, error) = match result {
+            Ok(status) => (status, None),
+            Err(err) => (None, Some(err.to_string())),
+        };
+        Self {
+            project,
+            status,
+            error,
+        }
+    }
+
+    /// Print counts of dirty, behind, and failed repos, failing when any of them failed.
+    pub fn summarize(reports: &[Self]) -> Result<(), MukdukError> {
+        let count = |f: fn(&Self) -> bool| reports.iter().filter(|r| f(r)).count();
+        let dirty = count(|r| r.status.as_ref().is_some_and(|s| s.dirty));
+        let behind = count(|r| r.status.as_ref().is_some_and(|s| s.behind > Some(0)));
+        let failed = count(|r| r.error.is_some());
+
+        eprintln!(
+            "{} repos: {}, {}, {}",
+            reports.len(),
+            format!("{dirty} dirty").yellow(),
+            format!("{behind} behind").blue(),
+            format!("{failed} failed").red(),
+        );
+        if failed > 0 {
+            return Err(MukdukError::GitFailed {
+                failed,
+                total: reports.len(),
+            });
+        }
+        Ok(())
+    }
+}
+
+impl Tabular for GitReport {
+    fn headers(&self) -> Vec<&'static str> {
+        vec!["name", "git", "error"]
+    }
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.project.name.clone(),
+            self.status
+                .as_ref()
+                .map(GitStatus::summary)
+                .unwrap_or_default(),
+            // Git errors span several lines, the first one is enough to keep the table readable.
+            self.error
+                .as_deref()
+                .and_then(|error| error.lines().next())
+                .unwrap_or_default()
+                .to_string(),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use pretty_assertions::assert_eq;
+
+    use super::{Git, GitOp, GitStatus};
+    use crate::runner::fake::FakeRunner;
+
+    #[test]
+    fn should_parse_branch_and_upstream_counts() {
+        let status = GitStatus::parse(
+            "# branch.oid abc\n# branch.head main\n# branch.upstream origin/main\n# branch.ab +2 -1\n? notes.md\n",
+        );
+        assert_eq!(
+            status,
+            GitStatus {
+                branch: Some("main".to_string()),
+                dirty: true,
+                ahead: Some(2),
+                behind: Some(1),
+            }
+        );
+
+        let status = GitStatus::parse("# branch.oid abc\n# branch.head (detached)\n");
+        assert_eq!(status, GitStatus::default());
+    }
+
+    #[test]
+    fn should_only_run_git_in_repos() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let runner = FakeRunner::new().on("git -C", 0, "# branch.head main\n", "");
+        let git = Git::new(&runner);
+
+        assert_eq!(git.status(dir.path())?, None);
+        assert!(runner.commands().is_empty());
+
+        fs::create_dir(dir.path().join(".git"))?;
+        let status = git.status(dir.path())?;
+        assert_eq!(status.and_then(|s| s.branch), Some("main".to_string()));
+        assert_eq!(
+            runner.calls()[0].command,
+            format!(
+                "git -C {} status --porcelain=v2 --branch",
+                PathBuf::from(dir.path()).to_string_lossy()
+            )
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn should_run_op_before_reading_status() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::create_dir(dir.path().join(".git"))?;
+        let runner =
+            FakeRunner::new().on("git -C", 0, "# branch.head main\n# branch.ab +0 -3\n", "");
+        let status = Git::new(&runner).run(GitOp::Pull, dir.path())?;
+
+        let commands: Vec<_> = runner
+            .commands()
+            .into_iter()
+            .map(|c| c.split_whitespace().skip(3).collect::<Vec<_>>().join(" "))
+            .collect();
+        assert_eq!(
+            commands,
+            vec!["pull --ff-only", "status --porcelain=v2 --branch"]
+        );
+        assert_eq!(status.map(|s| s.summary()), Some("main +0 -3".to_string()));
+        Ok(())
+    }
+}
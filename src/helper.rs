@@ -1,19 +1,36 @@
 use std::{
-    fs,
-    path::PathBuf,
+    fs, io,
+    path::{Component, Path, PathBuf},
     process::{Command, Output, Stdio},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex, PoisonError,
+    },
+    thread,
 };
 
 use anyhow::Result;
 use colored::Colorize;
+use inquire::{Confirm, InquireError};
 
-use crate::{fzf::FzfCmd, project::Project};
+use crate::{
+    config::{MukdukConfig, PickerConfig},
+    environment::Environment,
+    error::MukdukError,
+    fzf::FzfCmd,
+    git::{Git, GitStatus},
+    multiplexer::SanitizePolicy,
+    project::Project,
+    runner::SystemRunner,
+    sources::merge_sources,
+};
 
 pub fn wrap_command(command: &mut Command) -> Result<Output> {
     let output = command
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
-        .spawn()?
+        .spawn()
+        .map_err(|err| spawn_error(command, err))?
         .wait_with_output()?;
 
     // Use log crate to allow verbosity flag to control wrapped command logs.
@@ -26,79 +43,347 @@ pub fn wrap_command(command: &mut Command) -> Result<Output> {
     Ok(output)
 }
 
+/// A command failing to spawn almost always means the binary is not installed.
+pub fn spawn_error(command: &Command, err: io::Error) -> anyhow::Error {
+    if err.kind() == io::ErrorKind::NotFound {
+        MukdukError::MissingBinary(command.get_program().to_string_lossy().to_string()).into()
+    } else {
+        err.into()
+    }
+}
+
+/// Use the final component of a path as a project name.
+pub fn dir_name(path: &Path) -> Result<String, MukdukError> {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .ok_or_else(|| MukdukError::InvalidProjectPath(path.to_path_buf()))
+}
+
+/// `name` as a directory right inside of `dir`, refusing names like `../x` or `a/b` that would
+/// end up somewhere else.
+pub fn child_dir(dir: &Path, name: &str) -> Result<PathBuf, MukdukError> {
+    let mut components = Path::new(name).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(_)), None) => Ok(dir.join(name)),
+        _ => Err(MukdukError::InvalidProjectPath(PathBuf::from(name))),
+    }
+}
+
+/// The project at `project_dir`, or else one picked from `projects`.
 pub fn get_project(
-    projects_dir: PathBuf,
+    projects: &[Project],
+    config: &MukdukConfig,
     project_dir: &Option<PathBuf>,
     name: Option<String>,
+    tags: &[String],
+    query: Option<&str>,
 ) -> Result<Project> {
     project_dir.as_ref().map_or_else(
-        || pick_project(projects_dir),
-        |selected_project| {
-            Ok(Project::new(
-                selected_project.clone(),
-                name.unwrap_or_else(|| {
-                    selected_project
-                        .file_name()
-                        .expect("selected project should have a valid file / dir name.")
-                        .to_string_lossy()
-                        .to_string()
-                }),
-            ))
-        },
+        || pick_project(projects, config, tags, query),
+        |selected_project| project_at(config, selected_project.clone(), name),
     )
 }
 
-pub fn get_projects(proj_dir: &PathBuf) -> Result<Vec<Project>> {
-    let projects: Vec<_> = get_directories(proj_dir)?
-        .into_iter()
-        .map(|d| {
-            Project::new(
-                d.to_path_buf(),
-                d.file_name()
-                    .expect("file_name should be representable as a String")
-                    .to_string_lossy()
-                    .to_string(),
-            )
+/// The project at `path`, named `name` or else by its alias or directory name, with its tags.
+fn project_at(config: &MukdukConfig, path: PathBuf, name: Option<String>) -> Result<Project> {
+    let dir_name = dir_name(&path)?;
+    let settings = config.project_settings(&dir_name, &path);
+    let name = name.or(settings.alias).unwrap_or(dir_name);
+    Ok(Project::new(path, name).with_tags(settings.tags))
+}
+
+/// The project along with the command every pane of its session starts with. Finding it means
+/// looking at files in the project, so it is only done right before a session is opened.
+/// `container` runs the panes inside of the project's container even when its settings do not
+/// ask for it.
+pub fn with_session_command(
+    config: &MukdukConfig,
+    project: Project,
+    container: bool,
+) -> Result<Project> {
+    let settings = config.project_settings(&dir_name(&project.path)?, &project.path);
+    let container_settings = settings.container.unwrap_or_default();
+    let command = if container {
+        Some(container_settings.session_command(&project.path)?)
+    } else if let Some(remote) = project.remote() {
+        Some(remote.session_command())
+    } else if container_settings.always {
+        container_settings
+            .session_command(&project.path)
+            .map_err(|err| log::warn!("not opening {} in a container: {err}", project.name))
+            .ok()
+    } else {
+        None
+    };
+    // A container or remote host brings its own toolchain.
+    let command = command.or_else(|| {
+        let env = settings.env?;
+        let command = env.session_command(&project.path);
+        if command.is_none() && env != Environment::Auto {
+            log::warn!(
+                "{} has no files to load its {env:?} environment from",
+                project.name
+            );
+        }
+        command
+    });
+    Ok(project.with_command(command))
+}
+
+/// Every project in `proj_dir` and the configured sources carrying all of `tags`, with aliases
+/// and tags from the config applied.
+pub fn get_projects(
+    proj_dir: &PathBuf,
+    config: &MukdukConfig,
+    tags: &[String],
+) -> Result<Vec<Project>> {
+    let dirs = merge_sources(get_directories(proj_dir)?, &config.sources, &SystemRunner);
+    Ok(projects_at(config, dirs, tags))
+}
+
+/// Only the projects inside of `proj_dir`, for commands that move or delete project directories.
+pub fn get_local_projects(proj_dir: &PathBuf, config: &MukdukConfig) -> Result<Vec<Project>> {
+    Ok(projects_at(config, get_directories(proj_dir)?, &[]))
+}
+
+fn projects_at(config: &MukdukConfig, dirs: Vec<PathBuf>, tags: &[String]) -> Vec<Project> {
+    dirs.into_iter()
+        .filter_map(|dir| {
+            project_at(config, dir.clone(), None)
+                .map_err(|err| log::warn!("skipping {dir:?}: {err}"))
+                .ok()
         })
+        .filter(|project| project.has_tags(tags))
+        .collect()
+}
+
+/// Fail when another one of `projects` would be opened in the same session as `project`, since
+/// opening either of them would silently attach to the other's session.
+pub fn check_session_collision(
+    project: &Project,
+    projects: &[Project],
+    sanitize: &SanitizePolicy,
+) -> Result<(), MukdukError> {
+    let session = sanitize.apply(&project.name);
+    let others: Vec<PathBuf> = projects
+        .iter()
+        .filter(|other| other.path != project.path && sanitize.apply(&other.name) == session)
+        .map(|other| other.path.clone())
         .collect();
-    Ok(projects)
+    if others.is_empty() {
+        return Ok(());
+    }
+    Err(MukdukError::SessionCollision {
+        session,
+        paths: std::iter::once(project.path.clone())
+            .chain(others)
+            .collect(),
+    })
+}
+
+/// Sessions whose project has uncommitted changes or commits that were never pushed, along with
+/// the project and its git status. Sessions that do not belong to a project are skipped.
+pub fn sessions_with_unsaved_work(
+    sessions: &[String],
+    projects: &[Project],
+    sanitize: &SanitizePolicy,
+    git: &Git,
+) -> Vec<(String, Project, GitStatus)> {
+    sessions
+        .iter()
+        .filter_map(|session| {
+            let project = projects
+                .iter()
+                .find(|project| sanitize.apply(&project.name) == *session)?;
+            let status = git.status(&project.path).unwrap_or_else(|err| {
+                log::warn!("could not read git status of {}: {err}", project.name);
+                None
+            })?;
+            (!status.unsaved_work().is_empty()).then(|| (session.clone(), project.clone(), status))
+        })
+        .collect()
+}
+
+/// Ask a yes/no question defaulting to no. Without a terminal the answer is no, and the user is
+/// told to pass `--force` to `action`.
+pub fn confirm(question: &str, action: &str) -> Result<bool> {
+    match Confirm::new(question).with_default(false).prompt() {
+        Ok(confirmed) => Ok(confirmed),
+        Err(InquireError::NotTTY) => {
+            eprintln!(
+                "{}",
+                format!("Not a terminal, pass `--force` to {action}.").yellow()
+            );
+            Ok(false)
+        }
+        Err(InquireError::OperationCanceled | InquireError::OperationInterrupted) => Ok(false),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Find a project by its session name or its directory name.
+pub fn find_project(projects: Vec<Project>, name: &str) -> Result<Project, MukdukError> {
+    projects
+        .into_iter()
+        .find(|project| project.name == name || project.path.file_name() == Some(name.as_ref()))
+        .ok_or_else(|| MukdukError::ProjectNotFound(name.to_string()))
+}
+
+/// Move a directory, copying it when `to` is on another filesystem.
+pub fn move_dir(from: &Path, to: &Path) -> Result<()> {
+    if to.exists() {
+        return Err(MukdukError::AlreadyExists(to.to_path_buf()).into());
+    }
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    match fs::rename(from, to) {
+        Err(err) if err.kind() == io::ErrorKind::CrossesDevices => {
+            copy_dir(from, to)?;
+            fs::remove_dir_all(from)?;
+            Ok(())
+        }
+        result => Ok(result?),
+    }
 }
 
-pub fn pick_project(proj_dir: PathBuf) -> Result<Project> {
-    log::info!("Using project_dir: {:?}", &proj_dir);
+fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    fs::create_dir(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else if file_type.is_symlink() {
+            std::os::unix::fs::symlink(fs::read_link(entry.path())?, &target)?;
+        } else {
+            fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
 
-    let projects: Vec<_> = get_projects(&proj_dir)?;
-    let project_names = projects.iter().map(|p| p.name.clone()).collect::<Vec<_>>();
+/// The project `query` clearly means, ignoring case: an exact name, else the only name starting
+/// with it, else the single best fuzzy match. `None` when more than one project fits as well.
+pub fn match_query<'a>(projects: &'a [Project], query: &str) -> Option<&'a Project> {
+    let query = query.to_lowercase();
+    let names: Vec<String> = projects.iter().map(|p| p.name.to_lowercase()).collect();
+
+    if let Some(exact) = names.iter().position(|name| *name == query) {
+        return projects.get(exact);
+    }
+    let prefixed: Vec<usize> = (0..names.len())
+        .filter(|&i| names[i].starts_with(&query))
+        .collect();
+    match prefixed.as_slice() {
+        [only] => return projects.get(*only),
+        [] => {}
+        _ => return None,
+    }
+
+    let mut scored: Vec<(i64, usize)> = (0..names.len())
+        .filter_map(|i| fuzzy_score(&names[i], &query).map(|score| (score, i)))
+        .collect();
+    scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+    match scored.as_slice() {
+        [(_, only)] => projects.get(*only),
+        [(best, index), (next, _), ..] if best > next => projects.get(*index),
+        _ => None,
+    }
+}
+
+/// Score `query` as a subsequence of `name`, `None` when it is not one. Consecutive characters and
+/// characters at the start of a word score higher, and gaps between them cost a little.
+fn fuzzy_score(name: &str, query: &str) -> Option<i64> {
+    let name: Vec<char> = name.chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous: Option<usize> = None;
+    for wanted in query.chars() {
+        let found = position + name[position..].iter().position(|&c| c == wanted)?;
+        score += 1;
+        if previous.is_some_and(|previous| previous + 1 == found) {
+            score += 5;
+        }
+        if found == 0 || matches!(name[found - 1], '-' | '_' | '.' | ' ' | '/') {
+            score += 8;
+        }
+        score -= previous.map_or(found, |previous| found - previous - 1) as i64;
+        previous = Some(found);
+        position = found + 1;
+    }
+    Some(score)
+}
+
+/// Pick one of `projects` carrying all of `tags` with fzf, or skip the picker when `query`
+/// clearly names a single project.
+pub fn pick_project(
+    projects: &[Project],
+    config: &MukdukConfig,
+    tags: &[String],
+    query: Option<&str>,
+) -> Result<Project> {
+    let projects: Vec<Project> = projects
+        .iter()
+        .filter(|project| project.has_tags(tags))
+        .cloned()
+        .collect();
 
     log::debug!("projects: {projects:#?}");
 
-    let project_name = FzfCmd::new().find_vec(project_names)?;
+    if let Some(query) = query {
+        if let Some(project) = match_query(&projects, query) {
+            log::info!("{query:?} matched {}", project.name);
+            return Ok(project.clone());
+        }
+        log::info!("{query:?} is ambiguous, picking from matches");
+    }
+
+    // Tags are shown as a second tab separated column, padded so the columns line up.
+    let name_width = projects.iter().map(|p| p.name.len()).max().unwrap_or(0);
+    let picker_lines = projects
+        .iter()
+        .map(|p| format!("{}\t{}", p.name, p.tags.join(",")))
+        .collect::<Vec<_>>();
+
+    let mut fzf = FzfCmd::new();
+    fzf.picker(&config.picker)
+        .args(["--delimiter", "\t"])
+        .args([format!("--tabstop={}", name_width + 2)]);
+    if let Some(query) = query {
+        fzf.args(["--query", query]);
+    }
+    let picked = fzf.find_vec(picker_lines)?;
+    let project_name = picked.split('\t').next().unwrap_or_default();
 
     projects
         .iter()
         .find(|p| p.name == project_name)
-        .map_or_else(
-            || {
-                eprintln!("{}", "No project was selected.".red().bold());
-                std::process::exit(1);
-            },
-            |project| Ok(project.clone()),
-        )
+        .cloned()
+        .ok_or_else(|| MukdukError::NoSelection("project").into())
 }
 
-pub fn fzf_get_sessions(session_names: Vec<String>) -> Result<Vec<String>> {
+pub fn fzf_get_sessions(session_names: Vec<String>, picker: &PickerConfig) -> Result<Vec<String>> {
     if session_names.is_empty() {
         eprintln!("\n{}\n", "No sessions found to choose from.".blue().bold());
-        std::process::exit(0);
+        return Ok(Vec::new());
     }
 
-    Ok(FzfCmd::new()
+    let picked: Vec<String> = FzfCmd::new()
+        .picker(picker)
         .args(vec!["--phony", "--multi"])
         .find_vec(session_names)?
         .trim_end()
         .split('\n')
+        .filter(|s| !s.is_empty())
         .map(|s| s.to_string())
-        .collect())
+        .collect();
+
+    if picked.is_empty() {
+        return Err(MukdukError::NoSelection("session").into());
+    }
+    Ok(picked)
 }
 
 pub fn get_directories(path: &PathBuf) -> Result<Vec<PathBuf>> {
@@ -124,3 +409,255 @@ pub fn get_directories(path: &PathBuf) -> Result<Vec<PathBuf>> {
         })
         .collect())
 }
+
+/// Map `f` over `items` with at most `jobs` threads, keeping the order of `items`.
+pub fn parallel_map<T, R, F>(items: &[T], jobs: usize, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<R>>> = Mutex::new(items.iter().map(|_| None).collect());
+    thread::scope(|scope| {
+        for _ in 0..jobs.clamp(1, items.len().max(1)) {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(item) = items.get(index) else {
+                    break;
+                };
+                let result = f(item);
+                results.lock().unwrap_or_else(PoisonError::into_inner)[index] = Some(result);
+            });
+        }
+    });
+    results
+        .into_inner()
+        .unwrap_or_else(PoisonError::into_inner)
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use pretty_assertions::assert_eq;
+
+    use super::{
+        check_session_collision, child_dir, copy_dir, find_project, get_local_projects,
+        get_projects, match_query, move_dir, parallel_map, sessions_with_unsaved_work,
+    };
+    use crate::project::Project;
+    use crate::{
+        config::{ConfigFormat, ProjectSettings},
+        error::MukdukError,
+        git::Git,
+        multiplexer::Multiplexers,
+        runner::fake::FakeRunner,
+    };
+
+    #[test]
+    fn should_only_get_projects_with_every_tag() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        for name in ["mukduk", "infra", "notes"] {
+            fs::create_dir(dir.path().join(name))?;
+        }
+        fs::write(
+            dir.path().join("infra").join(ProjectSettings::LOCAL_FILE),
+            "tags = [\"work\"]\n",
+        )?;
+        let config = ConfigFormat::Yaml
+            .parse("projects:\n  mukduk:\n    tags: [rust, work]\n  infra:\n    tags: [ops]\n")?;
+
+        let mut names: Vec<_> = get_projects(&dir.path().to_path_buf(), &config, &["work".into()])?
+            .into_iter()
+            .map(|p| p.name)
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["infra", "mukduk"]);
+
+        let projects = get_projects(
+            &dir.path().to_path_buf(),
+            &config,
+            &["rust".into(), "work".into()],
+        )?;
+        assert_eq!(projects.len(), 1);
+        assert_eq!(projects[0].tags, vec!["rust", "work"]);
+        assert_eq!(
+            get_projects(&dir.path().to_path_buf(), &config, &[])?.len(),
+            3
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn should_skip_nameless_sources_and_keep_local_projects_local() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let projects_dir = dir.path().join("projects");
+        fs::create_dir_all(projects_dir.join("api"))?;
+        fs::create_dir(dir.path().join("elsewhere"))?;
+        let list = dir.path().join("projects.txt");
+        fs::write(&list, "/\nelsewhere\n")?;
+        let config = ConfigFormat::Yaml.parse(&format!(
+            "sources:\n  - type: file\n    path: {}\n",
+            list.to_string_lossy()
+        ))?;
+
+        let names = |projects: Vec<Project>| -> Vec<String> {
+            projects.into_iter().map(|p| p.name).collect()
+        };
+        assert_eq!(
+            names(get_projects(&projects_dir, &config, &[])?),
+            vec!["api", "elsewhere"]
+        );
+        assert_eq!(
+            names(get_local_projects(&projects_dir, &config)?),
+            vec!["api"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn should_detect_projects_sharing_a_session_name() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        for name in ["my.app", "my_app", "api-v1", "other"] {
+            fs::create_dir(dir.path().join(name))?;
+        }
+        let config = ConfigFormat::Yaml.parse("projects:\n  other:\n    alias: api-v1\n")?;
+        let projects = get_projects(&dir.path().to_path_buf(), &config, &[])?;
+        let find = |name: &str| {
+            projects
+                .iter()
+                .find(|p| p.path.ends_with(name))
+                .cloned()
+                .expect("project should exist")
+        };
+
+        let tmux = config.sanitize_policy(Multiplexers::Tmux);
+        let zellij = config.sanitize_policy(Multiplexers::Zellij);
+        assert!(matches!(
+            check_session_collision(&find("my.app"), &projects, &tmux),
+            Err(MukdukError::SessionCollision { session, paths }) if session == "my_app" && paths.len() == 2
+        ));
+        assert!(check_session_collision(&find("my.app"), &projects, &zellij).is_ok());
+        assert!(check_session_collision(&find("other"), &projects, &zellij).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn should_map_in_parallel_keeping_order() {
+        let items: Vec<u32> = (0..20).collect();
+        for jobs in [0, 1, 3, 50] {
+            assert_eq!(
+                parallel_map(&items, jobs, |i| i * 2),
+                (0..20).map(|i| i * 2).collect::<Vec<_>>()
+            );
+        }
+        assert!(parallel_map(&Vec::<u32>::new(), 4, |i| *i).is_empty());
+    }
+
+    #[test]
+    fn should_find_sessions_with_unsaved_work() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        for name in ["my.app", "clean", "plain"] {
+            fs::create_dir(dir.path().join(name))?;
+        }
+        fs::create_dir(dir.path().join("my.app").join(".git"))?;
+        fs::create_dir(dir.path().join("clean").join(".git"))?;
+        let projects = get_projects(
+            &dir.path().to_path_buf(),
+            &ConfigFormat::Yaml.parse("")?,
+            &[],
+        )?;
+        let my_app = dir.path().join("my.app");
+        let runner = FakeRunner::new()
+            .on(
+                &format!("git -C {}", my_app.to_string_lossy()),
+                0,
+                "# branch.head main\n# branch.ab +2 -0\n",
+                "",
+            )
+            .on("git -C", 0, "# branch.head main\n# branch.ab +0 -0\n", "");
+
+        let unsaved = sessions_with_unsaved_work(
+            &[
+                "my_app".into(),
+                "clean".into(),
+                "plain".into(),
+                "scratch".into(),
+            ],
+            &projects,
+            &Multiplexers::Tmux.default_sanitize(),
+            &Git::new(&runner),
+        );
+        assert_eq!(unsaved.len(), 1);
+        assert_eq!(unsaved[0].0, "my_app");
+        assert_eq!(unsaved[0].1.path, my_app);
+        assert_eq!(unsaved[0].2.ahead, Some(2));
+        Ok(())
+    }
+
+    #[test]
+    fn should_find_projects_by_alias_or_dir_name() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::create_dir(dir.path().join("infra"))?;
+        let config = ConfigFormat::Yaml.parse("projects:\n  infra:\n    alias: ops\n")?;
+        let projects = || get_projects(&dir.path().to_path_buf(), &config, &[]);
+
+        assert_eq!(find_project(projects()?, "ops")?.name, "ops");
+        assert_eq!(find_project(projects()?, "infra")?.name, "ops");
+        assert!(matches!(
+            find_project(projects()?, "nope"),
+            Err(MukdukError::ProjectNotFound(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn should_only_allow_names_of_direct_children() {
+        let dir = PathBuf::from("/p");
+        assert_eq!(child_dir(&dir, "api").ok(), Some(PathBuf::from("/p/api")));
+        for name in ["../x", "a/b", "/tmp/x", "..", ".", ""] {
+            assert!(child_dir(&dir, name).is_err(), "{name} should be refused");
+        }
+    }
+
+    #[test]
+    fn should_move_and_copy_dirs() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let from = dir.path().join("from");
+        fs::create_dir_all(from.join("src"))?;
+        fs::write(from.join("src").join("main.rs"), "fn main() {}")?;
+        std::os::unix::fs::symlink("src/main.rs", from.join("link"))?;
+
+        let copied = dir.path().join("copied");
+        copy_dir(&from, &copied)?;
+        assert_eq!(fs::read_to_string(copied.join("link"))?, "fn main() {}");
+
+        let moved = dir.path().join("archive").join("from");
+        move_dir(&from, &moved)?;
+        assert!(!from.exists());
+        assert!(moved.join("src").join("main.rs").is_file());
+        assert!(move_dir(&copied, &moved).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn should_match_query_exact_then_prefix_then_fuzzy() {
+        let projects: Vec<Project> = ["mukduk", "mukduk-web", "git_lib", "dotfiles", "docs"]
+            .into_iter()
+            .map(|name| Project::new(name.into(), name.to_string()))
+            .collect();
+        let matched = |query| match_query(&projects, query).map(|p| p.name.as_str());
+
+        assert_eq!(matched("mukduk"), Some("mukduk"));
+        assert_eq!(matched("MUKDUK-W"), Some("mukduk-web"));
+        assert_eq!(matched("git"), Some("git_lib"));
+        assert_eq!(matched("muk"), None);
+        assert_eq!(matched("gl"), Some("git_lib"));
+        assert_eq!(matched("dfs"), Some("dotfiles"));
+        assert_eq!(matched("zzz"), None);
+    }
+}
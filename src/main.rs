@@ -1,17 +1,42 @@
 use anyhow::Result;
+use clap::CommandFactory;
+use clap_complete::CompleteEnv;
 use cli::MukdukCli;
+use commands::completions::COMPLETE_VAR;
 
 mod config;
+mod container;
+mod details;
+mod environment;
+mod error;
+mod git;
 mod helper;
 mod multiplexer;
+mod output;
 mod project;
+mod remote;
+mod runner;
+mod scaffold;
+mod shell;
+mod sources;
+mod state;
 
 mod fzf;
 
 mod cli;
 mod commands;
 
-fn main() -> Result<()> {
+fn main() {
+    CompleteEnv::with_factory(MukdukCli::command)
+        .var(COMPLETE_VAR)
+        .complete();
+
+    if let Err(err) = run() {
+        std::process::exit(error::report(&err));
+    }
+}
+
+fn run() -> Result<()> {
     let cli = MukdukCli::init()?;
 
     cli.handle_cmd()?;
@@ -25,12 +50,16 @@ mod tests {
 
     use pretty_assertions::assert_eq;
 
-    use crate::project::Project;
+    use crate::{multiplexer::Multiplexers, project::Project};
 
     #[test]
-    fn should_update_project_name_with_underscores() {
+    fn should_keep_project_name_and_sanitize_tmux_session_name() {
+        let project = Project::new(PathBuf::from(""), ".test.test".to_string());
+        assert_eq!(project.get_name(), ".test.test".to_string());
         assert_eq!(
-            Project::new(PathBuf::from(""), ".test.test".to_string()).get_name(),
+            Multiplexers::Tmux
+                .default_sanitize()
+                .apply(&project.get_name()),
             "_test_test".to_string()
         )
     }
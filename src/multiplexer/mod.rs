@@ -1,57 +1,174 @@
 use anyhow::Result;
 use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::env;
 
-use crate::{commands::project::ProjectArgs, project::Project};
+use crate::{
+    commands::project::ProjectArgs,
+    error::MukdukError,
+    project::Project,
+    runner::{CommandRunner, SystemRunner},
+};
 
-use self::{tmux::Tmux, zellij::Zellij};
+use self::{
+    tmux::{Tmux, TmuxSocket},
+    zellij::Zellij,
+};
 
 pub mod tmux;
 pub mod zellij;
 
 pub trait Multiplexer {
-    fn open(self, proj_args: &ProjectArgs, project: Project) -> Result<()>;
-    fn get_sessions(self) -> Vec<String>;
-    fn kill_sessions(self, sessions: Vec<String>) -> Result<()>;
-    fn unique_session(self) -> Result<()>;
+    fn open(&self, proj_args: &ProjectArgs, project: Project) -> Result<()>;
+    fn get_sessions(&self) -> Result<Vec<String>>;
+    fn kill_sessions(&self, sessions: Vec<String>) -> Result<()>;
+    /// First of the unique session names (0-9) that is not taken, `None` when all of them are.
+    fn free_unique_session(&self) -> Result<Option<String>>;
+    fn rename_session(&self, from: &str, to: &str) -> Result<()>;
+    /// Session mukduk is running inside of, `None` when it is not inside one of this multiplexer.
+    fn current_session(&self) -> Result<Option<String>>;
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+/// Kill every session with `kill`, going on past failures and reporting them all at the end.
+fn kill_each(sessions: &[String], kill: impl Fn(&str) -> Result<()>) -> Result<()> {
+    let failed: Vec<&str> = sessions
+        .iter()
+        .filter(|session| match kill(session) {
+            Ok(()) => {
+                log::info!("Killed {session}.");
+                false
+            }
+            Err(err) => {
+                log::error!("Error while killing {session}: {err}");
+                true
+            }
+        })
+        .map(String::as_str)
+        .collect();
+    if failed.is_empty() {
+        return Ok(());
+    }
+    Err(MukdukError::KillFailed {
+        failed: failed.len(),
+        total: sessions.len(),
+        sessions: failed.join(", "),
+    }
+    .into())
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Multiplexers {
     Tmux,
     Zellij,
 }
 
-impl Multiplexer for Multiplexers {
-    fn open(self, proj_args: &ProjectArgs, project: Project) -> Result<()> {
-        match self {
-            Self::Tmux => {
-                Tmux::open(proj_args, project)?;
-            }
-            Self::Zellij => {
-                Zellij::open(proj_args, project)?;
-            }
+/// Settings shared by every backend, mostly coming from cli flags.
+#[derive(Debug, Clone, Default)]
+pub struct BackendOptions {
+    /// Create sessions without attaching or switching to them.
+    pub detach: bool,
+    /// tmux server to use instead of the default one.
+    pub tmux_socket: Option<TmuxSocket>,
+    /// How project names are turned into session names.
+    pub sanitize: SanitizePolicy,
+}
+
+/// Characters a multiplexer does not allow in session names, and what to use in their place.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SanitizePolicy {
+    /// Every character in this string is replaced.
+    pub replace: String,
+    #[serde(default = "SanitizePolicy::default_with")]
+    pub with: String,
+}
+
+impl Default for SanitizePolicy {
+    fn default() -> Self {
+        Self {
+            replace: String::new(),
+            with: Self::default_with(),
         }
-        Ok(())
+    }
+}
+
+impl SanitizePolicy {
+    fn default_with() -> String {
+        "_".to_string()
     }
 
-    fn get_sessions(self) -> Vec<String> {
-        match self {
-            Self::Tmux => Tmux::list_sessions(),
-            Self::Zellij => Zellij::list_sessions(),
+    pub fn apply(&self, name: &str) -> String {
+        name.chars()
+            .map(|c| {
+                if self.replace.contains(c) {
+                    self.with.clone()
+                } else {
+                    c.to_string()
+                }
+            })
+            .collect()
+    }
+}
+
+impl Multiplexers {
+    /// Used when the config file does not set a policy for this multiplexer.
+    pub fn default_sanitize(self) -> SanitizePolicy {
+        let replace = match self {
+            // tmux uses `.` and `:` in targets, so it silently rewrites them in session names.
+            Self::Tmux => ".:",
+            // zellij names a socket file after the session.
+            Self::Zellij => "/",
+        };
+        SanitizePolicy {
+            replace: replace.to_string(),
+            ..Default::default()
         }
     }
 
-    fn kill_sessions(self, sessions: Vec<String>) -> Result<()> {
-        match self {
-            Self::Tmux => Tmux::kill_sessions(&sessions),
-            Self::Zellij => Zellij::kill_sessions(&sessions),
+    /// Multiplexer of the session mukduk is running inside of, if any.
+    pub fn current() -> Option<Self> {
+        if env::var_os("TMUX").is_some() {
+            Some(Self::Tmux)
+        } else if env::var_os("ZELLIJ").is_some() {
+            Some(Self::Zellij)
+        } else {
+            None
         }
     }
 
-    fn unique_session(self) -> Result<()> {
+    /// Backend for this multiplexer that runs real commands.
+    pub fn backend(self, options: BackendOptions) -> Box<dyn Multiplexer> {
+        self.backend_with(&SystemRunner, options)
+    }
+
+    pub fn backend_with<'a>(
+        self,
+        runner: &'a dyn CommandRunner,
+        options: BackendOptions,
+    ) -> Box<dyn Multiplexer + 'a> {
         match self {
-            Self::Tmux => Tmux::unique_session(),
-            Self::Zellij => todo!(),
+            Self::Tmux => Box::new(Tmux::new(runner, options)),
+            Self::Zellij => Box::new(Zellij::new(runner, options)),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::{Multiplexers, SanitizePolicy};
+
+    #[test]
+    fn should_sanitize_names_per_multiplexer() {
+        assert_eq!(
+            Multiplexers::Tmux.default_sanitize().apply("my.app:v2"),
+            "my_app_v2"
+        );
+        assert_eq!(
+            Multiplexers::Zellij.default_sanitize().apply("my.app/v2"),
+            "my.app_v2"
+        );
+        assert_eq!(SanitizePolicy::default().apply("my.app"), "my.app");
+    }
+}
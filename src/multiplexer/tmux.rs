@@ -1,97 +1,197 @@
 use anyhow::Result;
-use colored::Colorize;
+use serde::{Deserialize, Serialize};
 use std::{
+    convert::Infallible,
     env,
     path::{Path, PathBuf},
     process::{Command, Output},
+    str::FromStr,
 };
 
 use crate::{
-    commands::project::ProjectArgs, config::ConfigEnvKey, helper::wrap_command, project::Project,
+    commands::project::ProjectArgs,
+    error::MukdukError,
+    multiplexer::{kill_each, BackendOptions, Multiplexer},
+    project::Project,
+    runner::CommandRunner,
 };
 
-pub struct Tmux;
+/// Which tmux server to talk to, when not the default one.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum TmuxSocket {
+    /// Socket name inside of tmux's socket dir, passed to tmux as `-L`.
+    Name(String),
+    /// Full path to the socket, passed to tmux as `-S`.
+    Path(PathBuf),
+}
+
+impl TmuxSocket {
+    /// Whether `$TMUX` (`socket_path,pid,session`) belongs to this server.
+    fn is_server_of(&self, tmux_env: &str) -> bool {
+        let socket_path = Path::new(tmux_env.split(',').next().unwrap_or_default());
+        match self {
+            Self::Name(name) => socket_path.file_name().is_some_and(|f| f == name.as_str()),
+            Self::Path(path) => {
+                socket_path == path
+                    || std::fs::canonicalize(path).is_ok_and(|path| {
+                        std::fs::canonicalize(socket_path).is_ok_and(|s| s == path)
+                    })
+            }
+        }
+    }
+}
+
+/// Anything that looks like a path is used with `-S`, everything else with `-L`.
+impl FromStr for TmuxSocket {
+    type Err = Infallible;
+
+    fn from_str(socket: &str) -> Result<Self, Self::Err> {
+        Ok(if socket.contains(std::path::MAIN_SEPARATOR) {
+            Self::Path(PathBuf::from(socket))
+        } else {
+            Self::Name(socket.to_string())
+        })
+    }
+}
+
+pub struct Tmux<'a> {
+    runner: &'a dyn CommandRunner,
+    options: BackendOptions,
+    in_session: bool,
+}
+
+impl<'a> Tmux<'a> {
+    pub fn new(runner: &'a dyn CommandRunner, options: BackendOptions) -> Self {
+        let in_session = Self::in_session(env::var("TMUX").ok().as_deref(), &options);
+        Self {
+            runner,
+            options,
+            in_session,
+        }
+    }
 
-impl Tmux {
-    pub fn open(_proj_args: &ProjectArgs, project: Project) -> Result<()> {
+    /// Only a client of the server we are targeting can be switched, being inside of some other
+    /// tmux server does not count.
+    fn in_session(tmux_env: Option<&str>, options: &BackendOptions) -> bool {
+        match (tmux_env, &options.tmux_socket) {
+            (None, _) => false,
+            (Some(_), None) => true,
+            (Some(tmux_env), Some(socket)) => socket.is_server_of(tmux_env),
+        }
+    }
+}
+
+impl Multiplexer for Tmux<'_> {
+    fn open(&self, _proj_args: &ProjectArgs, project: Project) -> Result<()> {
         log::info!(
             "Attempting to open Tmux session with project: {:?}!",
             project,
         );
 
-        if !Self::in_session() {
-            Self::create_new_attached_attach_if_exists(&project.get_name(), &project.get_path())?;
-        } else if Self::has_session(&project.get_name()) {
-            log::info!("Session '{}' already exists, opening.", project.get_name());
-            Self::switch(&project.get_name())?;
+        let name = self.options.sanitize.apply(&project.get_name());
+        let path = project.session_dir();
+        let command = project.command.as_deref();
+        if self.options.detach {
+            if !self.has_session(&name)? {
+                self.create_new_detached(&name, &path, command)?;
+            }
+        } else if !self.in_session {
+            // Attaching only returns once the user detaches, so a session command has to be set
+            // up on a detached session first.
+            if command.is_some() && !self.has_session(&name)? {
+                self.create_new_detached(&name, &path, command)?;
+            }
+            self.create_new_attached_attach_if_exists(&name, &path)?;
+        } else if self.has_session(&name)? {
+            log::info!("Session '{}' already exists, opening.", name);
+            self.switch(&name)?;
         } else {
             log::info!(
                 "Session '{}' does not already exist, creating and opening.",
-                project.get_name(),
+                name
             );
 
-            if Self::create_new_detached(&project.get_name(), &project.get_path())
-                .is_ok_and(|o| o.status.success())
-            {
-                Self::switch(&project.get_name())?;
-            } else {
-                eprintln!("{}", "Session failed to open.".red().bold());
-            }
+            self.create_new_detached(&name, &path, command)?;
+            self.switch(&name)?;
         }
 
         Ok(())
     }
 
-    pub fn list_sessions() -> Vec<String> {
-        String::from_utf8_lossy(
-            &wrap_command(Command::new("tmux").arg("ls"))
-                .expect("tmux should be able to list sessions")
-                .stdout,
-        )
-        .trim_end()
-        .split('\n')
-        .map(|s| s.split(':').collect::<Vec<_>>()[0].to_string())
-        .filter(|s| !s.is_empty())
-        .collect()
-    }
+    fn get_sessions(&self) -> Result<Vec<String>> {
+        let mut command = self.tmux();
+        command.arg("ls");
+        let output = self.runner.run(&mut command)?;
 
-    pub fn kill_sessions(sessions: &[String]) -> Result<()> {
-        sessions.iter().for_each(|s| {
-            if Self::kill_session(s).is_ok() {
-                if s.is_empty() {
-                    log::warn!("No session picked");
-                } else {
-                    log::info!("Killed {}.", s);
-                }
-            } else {
-                log::error!("Error while killing {}.", s)
+        if !output.status.success() {
+            // tmux reports a missing server as an error, but that just means there are no sessions.
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            if stderr.contains("no server running") || stderr.contains("error connecting to") {
+                return Ok(Vec::new());
             }
-        });
-        Ok(())
+            return Err(MukdukError::command_failed(&command, &output.stderr).into());
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .trim_end()
+            .split('\n')
+            .map(|s| s.split(':').next().unwrap_or_default().to_string())
+            .filter(|s| !s.is_empty())
+            .collect())
+    }
+
+    fn kill_sessions(&self, sessions: Vec<String>) -> Result<()> {
+        kill_each(&sessions, |session| self.kill_session(session))
     }
 
-    pub fn unique_session() -> Result<()> {
+    fn free_unique_session(&self) -> Result<Option<String>> {
         for i in 0..10 {
-            let name = &i.to_string();
-            if !Self::has_session(name) {
-                if Self::create_new_detached(name, &PathBuf::try_from(ConfigEnvKey::Home)?)
-                    .is_ok_and(|o| o.status.success())
-                {
-                    Self::switch(name)?;
-                    break;
-                } else {
-                    eprintln!("{}", "Session failed to open.".red().bold());
-                }
+            let name = i.to_string();
+            if !self.has_session(&name)? {
+                return Ok(Some(name));
             }
         }
+        Ok(None)
+    }
+
+    fn rename_session(&self, from: &str, to: &str) -> Result<()> {
+        self.runner.run_checked(self.tmux().args([
+            "rename-session",
+            "-t",
+            &format!("={from}"),
+            to,
+        ]))?;
         Ok(())
     }
+
+    fn current_session(&self) -> Result<Option<String>> {
+        if !self.in_session {
+            return Ok(None);
+        }
+        let output = self
+            .runner
+            .run_checked(self.tmux().args(["display-message", "-p", "#S"]))?;
+        Ok(Some(
+            String::from_utf8_lossy(&output.stdout).trim().to_string(),
+        ))
+    }
 }
 
-impl Tmux {
+impl Tmux<'_> {
+    /// Every tmux invocation goes through here so the server socket is always respected.
+    fn tmux(&self) -> Command {
+        let mut command = Command::new("tmux");
+        match &self.options.tmux_socket {
+            Some(TmuxSocket::Name(name)) => command.args(["-L", name]),
+            Some(TmuxSocket::Path(path)) => command.arg("-S").arg(path),
+            None => &mut command,
+        };
+        command
+    }
+
     #[allow(dead_code)] // This will likely be needed eventually.
-    fn create_new_detached_attach_if_exists(name: &str, path: &Path) -> Result<Output> {
-        wrap_command(Command::new("tmux").args([
+    fn create_new_detached_attach_if_exists(&self, name: &str, path: &Path) -> Result<Output> {
+        self.runner.run_checked(self.tmux().args([
             "new-session",
             "-Ad",
             "-s",
@@ -101,8 +201,8 @@ impl Tmux {
         ]))
     }
 
-    fn create_new_attached_attach_if_exists(name: &str, path: &Path) -> Result<Output> {
-        wrap_command(Command::new("tmux").args([
+    fn create_new_attached_attach_if_exists(&self, name: &str, path: &Path) -> Result<Output> {
+        self.runner.run_checked(self.tmux().args([
             "new-session",
             "-A",
             "-s",
@@ -112,41 +212,361 @@ impl Tmux {
         ]))
     }
 
-    fn create_new_detached(name: &str, path: &Path) -> Result<Output> {
-        wrap_command(Command::new("tmux").args([
-            "new-session",
-            "-d",
-            "-s",
-            name,
-            "-c",
-            path.to_str().unwrap_or_default(),
-        ]))
+    /// Windows and panes created later on run `command` too, through the session's
+    /// `default-command`.
+    fn create_new_detached(
+        &self,
+        name: &str,
+        path: &Path,
+        command: Option<&str>,
+    ) -> Result<Output> {
+        let output = self.runner.run_checked(
+            self.tmux()
+                .args([
+                    "new-session",
+                    "-d",
+                    "-s",
+                    name,
+                    "-c",
+                    path.to_str().unwrap_or_default(),
+                ])
+                .args(command),
+        )?;
+        if let Some(command) = command {
+            self.runner.run_checked(self.tmux().args([
+                "set-option",
+                "-t",
+                name,
+                "default-command",
+                command,
+            ]))?;
+        }
+        Ok(output)
     }
 
-    fn switch(to_name: &str) -> Result<Output> {
-        wrap_command(Command::new("tmux").args(["switch-client", "-t", to_name]))
+    fn switch(&self, to_name: &str) -> Result<Output> {
+        self.runner
+            .run_checked(self.tmux().args(["switch-client", "-t", to_name]))
     }
 
-    fn has_session(project_name: &str) -> bool {
-        let output = wrap_command(Command::new("tmux").args([
+    fn has_session(&self, project_name: &str) -> Result<bool> {
+        let output = self.runner.run(self.tmux().args([
             "has-session",
             "-t",
             &format!("={}", project_name),
-        ]));
+        ]))?;
 
-        output.is_ok_and(|o| o.status.success())
+        Ok(output.status.success())
     }
 
-    fn kill_session(project_name: &str) -> Result<()> {
-        wrap_command(Command::new("tmux").args([
+    fn kill_session(&self, project_name: &str) -> Result<()> {
+        self.runner.run_checked(self.tmux().args([
             "kill-session",
             "-t",
             &format!("={}", project_name),
         ]))?;
         Ok(())
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use pretty_assertions::assert_eq;
+
+    use super::{Tmux, TmuxSocket};
+    use crate::{
+        commands::project::ProjectArgs,
+        error::MukdukError,
+        multiplexer::{BackendOptions, Multiplexer, Multiplexers},
+        project::Project,
+        runner::fake::FakeRunner,
+    };
+
+    fn tmux(runner: &FakeRunner, in_session: bool) -> Tmux<'_> {
+        Tmux {
+            runner,
+            options: BackendOptions::default(),
+            in_session,
+        }
+    }
+
+    fn project() -> Project {
+        Project::new(PathBuf::from("/projects/mukduk"), "mukduk".to_string())
+    }
+
+    fn proj_args() -> ProjectArgs {
+        ProjectArgs {
+            name: None,
+            project_dir: None,
+        }
+    }
+
+    #[test]
+    fn should_attach_when_outside_tmux() -> anyhow::Result<()> {
+        let runner = FakeRunner::new();
+        tmux(&runner, false).open(&proj_args(), project())?;
+
+        assert_eq!(
+            runner.commands(),
+            vec!["tmux new-session -A -s mukduk -c /projects/mukduk"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn should_switch_to_existing_session() -> anyhow::Result<()> {
+        let runner = FakeRunner::new();
+        tmux(&runner, true).open(&proj_args(), project())?;
+
+        assert_eq!(
+            runner.commands(),
+            vec![
+                "tmux has-session -t =mukduk",
+                "tmux switch-client -t mukduk"
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn should_create_then_switch_to_missing_session() -> anyhow::Result<()> {
+        let runner = FakeRunner::new().on("tmux has-session", 1, "", "can't find session");
+        tmux(&runner, true).open(&proj_args(), project())?;
+
+        assert_eq!(
+            runner.commands(),
+            vec![
+                "tmux has-session -t =mukduk",
+                "tmux new-session -d -s mukduk -c /projects/mukduk",
+                "tmux switch-client -t mukduk"
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn should_run_session_command_in_every_pane() -> anyhow::Result<()> {
+        let runner = FakeRunner::new().on("tmux has-session", 1, "", "can't find session");
+        let project = Project::new(PathBuf::from("ssh://devbox/srv/api"), "api".to_string())
+            .with_command(Some("ssh -t devbox".to_string()));
+        let home = project.session_dir();
+        tmux(&runner, false).open(&proj_args(), project)?;
+
+        assert_eq!(
+            runner.commands(),
+            vec![
+                "tmux has-session -t =api".to_string(),
+                format!(
+                    "tmux new-session -d -s api -c {} ssh -t devbox",
+                    home.to_string_lossy()
+                ),
+                "tmux set-option -t api default-command ssh -t devbox".to_string(),
+                format!("tmux new-session -A -s api -c {}", home.to_string_lossy()),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn should_rename_exact_session() -> anyhow::Result<()> {
+        let runner = FakeRunner::new();
+        tmux(&runner, false).rename_session("api", "api-v2")?;
+        assert_eq!(
+            runner.commands(),
+            vec!["tmux rename-session -t =api api-v2"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn should_only_read_current_session_inside_tmux() -> anyhow::Result<()> {
+        let runner = FakeRunner::new().on("tmux display-message", 0, "mukduk\n", "");
+        assert_eq!(tmux(&runner, false).current_session()?, None);
+        assert!(runner.commands().is_empty());
+
+        assert_eq!(
+            tmux(&runner, true).current_session()?,
+            Some("mukduk".to_string())
+        );
+        assert_eq!(runner.commands(), vec!["tmux display-message -p #S"]);
+        Ok(())
+    }
+
+    #[test]
+    fn should_not_switch_when_session_fails_to_open() {
+        let runner = FakeRunner::new().on("tmux has-session", 1, "", "").on(
+            "tmux new-session",
+            1,
+            "",
+            "duplicate session: mukduk",
+        );
+        let err = tmux(&runner, true)
+            .open(&proj_args(), project())
+            .expect_err("new-session failed");
+
+        assert!(matches!(
+            err.downcast_ref::<MukdukError>(),
+            Some(MukdukError::CommandFailed { stderr, .. }) if stderr == "duplicate session: mukduk"
+        ));
+        assert_eq!(runner.commands().len(), 2);
+    }
+
+    #[test]
+    fn should_list_session_names() -> anyhow::Result<()> {
+        let runner = FakeRunner::new().on(
+            "tmux ls",
+            0,
+            "0: 1 windows (created Sat Oct 18 10:00:00 2026)\nmukduk: 2 windows (created Sat Oct 18 10:00:00 2026) (attached)\n",
+            "",
+        );
+
+        assert_eq!(tmux(&runner, true).get_sessions()?, vec!["0", "mukduk"]);
+        Ok(())
+    }
+
+    #[test]
+    fn should_list_no_sessions_without_server() -> anyhow::Result<()> {
+        let runner =
+            FakeRunner::new().on("tmux ls", 1, "", "no server running on /tmp/tmux-0/default");
+
+        assert!(tmux(&runner, true).get_sessions()?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn should_kill_every_session_even_after_failure() -> anyhow::Result<()> {
+        let runner = FakeRunner::new().on("tmux kill-session -t =a", 1, "", "no such session");
+        let err = tmux(&runner, true)
+            .kill_sessions(vec!["a".to_string(), "b".to_string()])
+            .expect_err("killing `a` failed");
+
+        assert!(matches!(
+            err.downcast_ref::<MukdukError>(),
+            Some(MukdukError::KillFailed {
+                failed: 1,
+                total: 2,
+                ..
+            })
+        ));
+        assert_eq!(
+            runner.commands(),
+            vec!["tmux kill-session -t =a", "tmux kill-session -t =b"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn should_find_first_free_unique_session() -> anyhow::Result<()> {
+        let runner = FakeRunner::new()
+            .on("tmux has-session -t =0", 0, "", "")
+            .on("tmux has-session", 1, "", "");
+        assert_eq!(
+            tmux(&runner, true).free_unique_session()?,
+            Some("1".to_string())
+        );
+        assert_eq!(
+            runner.commands(),
+            vec!["tmux has-session -t =0", "tmux has-session -t =1"]
+        );
+
+        let runner = FakeRunner::new();
+        assert_eq!(tmux(&runner, true).free_unique_session()?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn should_sanitize_session_name() -> anyhow::Result<()> {
+        let runner = FakeRunner::new().on("tmux has-session", 1, "", "");
+        let tmux = Tmux {
+            runner: &runner,
+            options: BackendOptions {
+                detach: true,
+                sanitize: Multiplexers::Tmux.default_sanitize(),
+                ..Default::default()
+            },
+            in_session: false,
+        };
+        tmux.open(
+            &proj_args(),
+            Project::new(PathBuf::from("/projects/my.app"), "my.app".to_string()),
+        )?;
+
+        assert_eq!(
+            runner.commands(),
+            vec![
+                "tmux has-session -t =my_app",
+                "tmux new-session -d -s my_app -c /projects/my.app"
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn should_create_detached_session_on_socket() -> anyhow::Result<()> {
+        let runner = FakeRunner::new().on("tmux -L work has-session", 1, "", "");
+        let tmux = Tmux {
+            runner: &runner,
+            options: BackendOptions {
+                detach: true,
+                tmux_socket: Some(TmuxSocket::Name("work".to_string())),
+                ..Default::default()
+            },
+            in_session: true,
+        };
+        tmux.open(&proj_args(), project())?;
+
+        assert_eq!(
+            runner.commands(),
+            vec![
+                "tmux -L work has-session -t =mukduk",
+                "tmux -L work new-session -d -s mukduk -c /projects/mukduk"
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn should_pass_socket_path_to_every_command() -> anyhow::Result<()> {
+        let runner = FakeRunner::new().on("tmux -S /tmp/pair has-session", 1, "", "");
+        let tmux = Tmux {
+            runner: &runner,
+            options: BackendOptions {
+                tmux_socket: Some("/tmp/pair".parse()?),
+                ..Default::default()
+            },
+            in_session: true,
+        };
+        tmux.open(&proj_args(), project())?;
+        tmux.get_sessions()?;
+        tmux.kill_sessions(vec!["mukduk".to_string()])?;
+
+        assert_eq!(
+            runner.commands(),
+            vec![
+                "tmux -S /tmp/pair has-session -t =mukduk",
+                "tmux -S /tmp/pair new-session -d -s mukduk -c /projects/mukduk",
+                "tmux -S /tmp/pair switch-client -t mukduk",
+                "tmux -S /tmp/pair ls",
+                "tmux -S /tmp/pair kill-session -t =mukduk"
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn should_only_switch_clients_of_the_same_server() {
+        let work = BackendOptions {
+            tmux_socket: Some(TmuxSocket::Name("work".to_string())),
+            ..Default::default()
+        };
+        let inside_default = Some("/tmp/tmux-1000/default,123,0");
+        let inside_work = Some("/tmp/tmux-1000/work,456,0");
 
-    fn in_session() -> bool {
-        env::var("TMUX").is_ok()
+        assert!(!Tmux::in_session(None, &BackendOptions::default()));
+        assert!(Tmux::in_session(inside_default, &BackendOptions::default()));
+        assert!(!Tmux::in_session(inside_default, &work));
+        assert!(Tmux::in_session(inside_work, &work));
     }
 }
@@ -1,101 +1,383 @@
 use anyhow::Result;
-use colored::Colorize;
 use std::{
-    env,
-    path::Path,
+    env, fs,
+    io::{self, Write},
+    os::unix::fs::OpenOptionsExt,
+    path::{Path, PathBuf},
     process::{Command, Output},
 };
 
-use crate::{commands::project::ProjectArgs, helper::wrap_command, project::Project};
+use crate::{
+    commands::project::ProjectArgs,
+    config::ConfigEnvKey,
+    error::MukdukError,
+    multiplexer::{kill_each, BackendOptions, Multiplexer},
+    project::Project,
+    runner::CommandRunner,
+};
+
+pub struct Zellij<'a> {
+    runner: &'a dyn CommandRunner,
+    options: BackendOptions,
+    in_session: bool,
+    /// Where session command scripts are written, `$XDG_STATE_HOME/mukduk/shells` when not set.
+    shells_dir: Option<PathBuf>,
+}
+
+impl<'a> Zellij<'a> {
+    pub fn new(runner: &'a dyn CommandRunner, options: BackendOptions) -> Self {
+        Self {
+            runner,
+            options,
+            in_session: env::var("ZELLIJ").is_ok(),
+            shells_dir: None,
+        }
+    }
 
-pub struct Zellij;
+    fn shells_dir(&self) -> Result<PathBuf> {
+        match &self.shells_dir {
+            Some(dir) => Ok(dir.clone()),
+            None => Ok(PathBuf::try_from(ConfigEnvKey::XDGState)?
+                .join("mukduk")
+                .join("shells")),
+        }
+    }
+}
 
-impl Zellij {
-    pub fn open(_proj_args: &ProjectArgs, project: Project) -> Result<()> {
+impl Multiplexer for Zellij<'_> {
+    fn open(&self, _proj_args: &ProjectArgs, project: Project) -> Result<()> {
         log::info!("creating Zellij session with project: {:?}!", project);
 
-        if Self::not_in() {
-            Self::create_attached(&project.get_name(), &project.get_path())?;
-        } else {
-            eprintln!("{}", "\nZellij does not currently have support for switching sessions while inside an active session.\n\nTry detaching from your current session, and try again.\n".yellow().bold())
+        let name = self.options.sanitize.apply(&project.get_name());
+        let path = project.session_dir();
+        let shell = project
+            .command
+            .as_deref()
+            .map(|command| shell_script(&self.shells_dir()?, &name, command))
+            .transpose()?;
+        if self.options.detach {
+            self.create_background(&name, &path, shell.as_deref())?;
+            return Ok(());
+        }
+        if self.in_session {
+            return Err(MukdukError::NestedSession("zellij").into());
         }
+        self.create_attached(&name, &path, shell.as_deref())?;
 
         Ok(())
     }
 
-    pub fn list_sessions() -> Vec<String> {
-        String::from_utf8_lossy(
-            &wrap_command(Command::new("zellij").arg("ls"))
-                .expect("zellij should be able to list sessions")
-                .stdout,
-        )
-        .trim_end()
-        .split('\n')
-        .map(|s| s.to_string())
-        .filter(|s| !s.is_empty())
-        .collect()
+    fn get_sessions(&self) -> Result<Vec<String>> {
+        let mut command = Command::new("zellij");
+        command.arg("ls");
+        let output = self.runner.run(&mut command)?;
+
+        if !output.status.success() {
+            if String::from_utf8_lossy(&output.stderr).contains("No active zellij sessions found.")
+            {
+                return Ok(Vec::new());
+            }
+            return Err(MukdukError::command_failed(&command, &output.stderr).into());
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .trim_end()
+            .split('\n')
+            .map(|s| s.to_string())
+            .filter(|s| !s.is_empty())
+            .collect())
+    }
+
+    fn kill_sessions(&self, sessions: Vec<String>) -> Result<()> {
+        kill_each(&sessions, |session| self.kill_session(session))
+    }
+
+    fn free_unique_session(&self) -> Result<Option<String>> {
+        Err(MukdukError::Unsupported {
+            multiplexer: "zellij",
+            operation: "unique sessions",
+        }
+        .into())
     }
 
-    pub fn kill_sessions(sessions: &[String]) -> Result<()> {
-        sessions.iter().for_each(|s| {
-            if Self::kill_session(s).is_ok() {
-                log::info!("killed {}", s)
+    fn rename_session(&self, from: &str, to: &str) -> Result<()> {
+        self.runner.run_checked(Command::new("zellij").args([
+            "--session",
+            from,
+            "action",
+            "rename-session",
+            to,
+        ]))?;
+        Ok(())
+    }
+
+    fn current_session(&self) -> Result<Option<String>> {
+        Ok(self
+            .in_session
+            .then(|| env::var("ZELLIJ_SESSION_NAME").ok())
+            .flatten())
+    }
+}
+
+/// Zellij only takes a program as the default shell, so session commands are wrapped in a
+/// script in `dir` that lives as long as the session might.
+fn shell_script(dir: &Path, session: &str, command: &str) -> Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    // Session names can hold anything the sanitize policy lets through, `/` included.
+    let file_name: String = session
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || "-_.".contains(c) {
+                c
             } else {
-                log::error!("error while killing {}", s)
+                '_'
             }
-        });
-        Ok(())
+        })
+        .collect();
+    let script = dir.join(format!("{file_name}.sh"));
+    // A fresh file is created so a link left in its place is replaced instead of followed.
+    match fs::remove_file(&script) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err.into()),
+        _ => {}
     }
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o755)
+        .open(&script)?
+        .write_all(format!("#!/bin/sh\n{command}\n").as_bytes())?;
+    Ok(script)
 }
 
-impl Zellij {
-    fn create_attached(name: &str, path: &Path) -> Result<Output> {
-        wrap_command(
+impl Zellij<'_> {
+    /// New panes of a session started with `shell` run it instead of the user's shell.
+    fn with_shell<'c>(command: &'c mut Command, shell: Option<&Path>) -> &'c mut Command {
+        if let Some(shell) = shell {
+            command.arg("options").arg("--default-shell").arg(shell);
+        }
+        command
+    }
+
+    fn create_attached(&self, name: &str, path: &Path, shell: Option<&Path>) -> Result<Output> {
+        self.runner.run_checked(Self::with_shell(
             Command::new("zellij")
                 .args(["a", "-c", name])
                 .current_dir(path.to_str().unwrap_or_default()),
-        )
+            shell,
+        ))
+    }
+
+    fn create_background(&self, name: &str, path: &Path, shell: Option<&Path>) -> Result<Output> {
+        self.runner.run_checked(Self::with_shell(
+            Command::new("zellij")
+                .args(["attach", "--create-background", name])
+                .current_dir(path.to_str().unwrap_or_default()),
+            shell,
+        ))
     }
 
     #[allow(dead_code)] // This will likely be needed eventually.
-    fn has_session(project_name: &str) -> bool {
-        let output = Command::new("zellij")
-            .arg("ls")
-            .output()
-            .expect("zellij was not able to print list of sessions.");
-        match output.status.success() {
-            true => {
-                assert_ne!(project_name, "", "Zellij session name cannot be empty. The sessions list will contain \"\" due to split('\n').");
-                if String::from_utf8_lossy(&output.stdout)
-                    .split('\n')
-                    .any(|session_name| session_name == project_name)
-                {
-                    return true;
-                }
-                false
-            }
-            false => {
-                let error_msg = String::from_utf8_lossy(&output.stderr);
-                if error_msg.contains("No active zellij sessions found.") {
-                    false
-                } else {
-                    eprintln!(
-                        "zellij command failed with exit code: {}, and error msg: {}\n",
-                        output.status,
-                        String::from_utf8_lossy(&output.stderr)
-                    );
-                    panic!("The command 'zellij ls' should not fail unless zellij is not present on the machine.")
-                }
-            }
+    fn has_session(&self, project_name: &str) -> Result<bool> {
+        Ok(self
+            .get_sessions()?
+            .iter()
+            .any(|session_name| session_name == project_name))
+    }
+
+    fn kill_session(&self, project_name: &str) -> Result<()> {
+        self.runner
+            .run_checked(Command::new("zellij").args(["kill-session", project_name]))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use pretty_assertions::assert_eq;
+
+    use super::{shell_script, Zellij};
+    use crate::{
+        commands::project::ProjectArgs,
+        error::MukdukError,
+        multiplexer::{BackendOptions, Multiplexer},
+        project::Project,
+        runner::fake::FakeRunner,
+    };
+
+    fn zellij(runner: &FakeRunner, in_session: bool) -> Zellij<'_> {
+        Zellij {
+            runner,
+            options: BackendOptions::default(),
+            in_session,
+            shells_dir: None,
+        }
+    }
+
+    fn proj_args() -> ProjectArgs {
+        ProjectArgs {
+            name: None,
+            project_dir: None,
         }
     }
 
-    fn kill_session(project_name: &str) -> Result<()> {
-        wrap_command(Command::new("zellij").args(["kill-session", project_name]))?;
+    #[test]
+    fn should_create_or_attach_from_project_dir() -> anyhow::Result<()> {
+        let runner = FakeRunner::new();
+        zellij(&runner, false).open(
+            &proj_args(),
+            Project::new(PathBuf::from("/projects/mukduk"), "mukduk".to_string()),
+        )?;
+
+        let calls = runner.calls();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].command, "zellij a -c mukduk");
+        assert_eq!(
+            calls[0].current_dir,
+            Some(PathBuf::from("/projects/mukduk"))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn should_create_background_session_when_detached() -> anyhow::Result<()> {
+        let runner = FakeRunner::new();
+        let zellij = Zellij {
+            runner: &runner,
+            options: BackendOptions {
+                detach: true,
+                ..Default::default()
+            },
+            in_session: true,
+            shells_dir: None,
+        };
+        zellij.open(
+            &proj_args(),
+            Project::new(PathBuf::from("/projects/mukduk"), "mukduk".to_string()),
+        )?;
+
+        assert_eq!(
+            runner.commands(),
+            vec!["zellij attach --create-background mukduk"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn should_run_session_command_from_script() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let runner = FakeRunner::new();
+        let zellij = Zellij {
+            runner: &runner,
+            options: BackendOptions {
+                detach: true,
+                ..Default::default()
+            },
+            in_session: false,
+            shells_dir: Some(dir.path().to_path_buf()),
+        };
+        let command = "docker compose up -d app && exec docker compose exec app sh";
+        zellij.open(
+            &proj_args(),
+            Project::new(PathBuf::from("/projects/shop"), "shop".to_string())
+                .with_command(Some(command.to_string())),
+        )?;
+
+        let script = dir.path().join("shop.sh");
+        assert_eq!(
+            fs::read_to_string(&script)?,
+            format!("#!/bin/sh\n{command}\n")
+        );
+        assert_eq!(
+            runner.commands(),
+            vec![format!(
+                "zellij attach --create-background shop options --default-shell {}",
+                script.to_string_lossy()
+            )]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn should_keep_scripts_inside_of_their_dir() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let shells = dir.path().join("shells");
+        assert_eq!(
+            shell_script(&shells, "../../escaped", "true")?,
+            shells.join(".._.._escaped.sh")
+        );
+        assert!(!dir.path().join("escaped.sh").exists());
+
+        let target = dir.path().join("target");
+        fs::write(&target, "untouched")?;
+        std::os::unix::fs::symlink(&target, shells.join("linked.sh"))?;
+        shell_script(&shells, "linked", "true")?;
+        assert_eq!(fs::read_to_string(&target)?, "untouched");
+        assert_eq!(
+            fs::read_to_string(shells.join("linked.sh"))?,
+            "#!/bin/sh\ntrue\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn should_refuse_to_open_inside_session() {
+        let runner = FakeRunner::new();
+        let err = zellij(&runner, true)
+            .open(
+                &proj_args(),
+                Project::new(PathBuf::from("/projects/mukduk"), "mukduk".to_string()),
+            )
+            .expect_err("nested sessions are not supported");
+
+        assert!(matches!(
+            err.downcast_ref::<MukdukError>(),
+            Some(MukdukError::NestedSession("zellij"))
+        ));
+        assert!(runner.commands().is_empty());
+    }
+
+    #[test]
+    fn should_list_sessions_and_treat_none_as_empty() -> anyhow::Result<()> {
+        let runner = FakeRunner::new().on("zellij ls", 0, "mukduk\nscratch\n", "");
+        assert_eq!(
+            zellij(&runner, false).get_sessions()?,
+            vec!["mukduk", "scratch"]
+        );
+
+        let runner = FakeRunner::new().on("zellij ls", 1, "", "No active zellij sessions found.");
+        assert!(zellij(&runner, false).get_sessions()?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn should_rename_session_from_outside() -> anyhow::Result<()> {
+        let runner = FakeRunner::new();
+        zellij(&runner, false).rename_session("api", "api-v2")?;
+        assert_eq!(
+            runner.commands(),
+            vec!["zellij --session api action rename-session api-v2"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn should_kill_each_session() -> anyhow::Result<()> {
+        let runner = FakeRunner::new();
+        zellij(&runner, false).kill_sessions(vec!["a".to_string(), "b".to_string()])?;
+
+        assert_eq!(
+            runner.commands(),
+            vec!["zellij kill-session a", "zellij kill-session b"]
+        );
         Ok(())
     }
 
-    fn not_in() -> bool {
-        env::var("ZELLIJ").is_err()
+    #[test]
+    fn should_not_support_unique_sessions() {
+        let runner = FakeRunner::new();
+        assert!(zellij(&runner, false).free_unique_session().is_err());
+        assert!(runner.commands().is_empty());
     }
 }
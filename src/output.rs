@@ -0,0 +1,297 @@
+use anyhow::Result;
+use clap::{Args, ValueEnum};
+use colored::Colorize;
+use serde::Serialize;
+use std::{fmt::Debug, io::IsTerminal};
+
+use crate::error::MukdukError;
+
+/// Something that can be printed as one row of a table.
+pub trait Tabular {
+    /// Column names, which may depend on what was collected for the row.
+    fn headers(&self) -> Vec<&'static str>;
+    fn row(&self) -> Vec<String>;
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Rust Debug print.
+    Debug,
+    /// Pretty printed json.
+    Json,
+    /// Raw printed json.
+    JsonR,
+    /// yaml.
+    Yaml,
+    /// Aligned columns, colored on a terminal.
+    Table,
+    /// Comma separated values with a header row.
+    Csv,
+    /// Tab separated values with a header row.
+    Tsv,
+}
+
+/// Output flags shared by every list command.
+#[derive(Args, Debug)]
+pub struct OutputArgs {
+    #[arg(short, long, value_enum)]
+    /// Defaults to `table` when printing to a terminal, and `debug` otherwise.
+    pub output: Option<OutputFormat>,
+
+    #[arg(long, conflicts_with = "output")]
+    /// Print one line per item, replacing `{column}` with its value, like '{name}\t{path}'.
+    pub template: Option<String>,
+}
+
+impl OutputArgs {
+    pub fn format(&self) -> OutputFormat {
+        self.output.unwrap_or_else(|| {
+            if std::io::stdout().is_terminal() {
+                OutputFormat::Table
+            } else {
+                OutputFormat::Debug
+            }
+        })
+    }
+
+    pub fn print<T: Serialize + Tabular + Debug>(&self, items: &[T]) -> Result<()> {
+        if let Some(template) = &self.template {
+            for item in items {
+                println!("{}", render_template(template, item)?);
+            }
+            return Ok(());
+        }
+        match self.format() {
+            OutputFormat::Debug => println!("{items:#?}"),
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(items)?),
+            OutputFormat::JsonR => println!("{}", serde_json::to_string(items)?),
+            OutputFormat::Yaml => println!("{}", serde_yaml::to_string(items)?),
+            OutputFormat::Table => {
+                let color = std::io::stdout().is_terminal();
+                if !items.is_empty() {
+                    println!("{}", table(items, color));
+                }
+            }
+            OutputFormat::Csv => print!("{}", separated(items, ',', csv_cell)),
+            OutputFormat::Tsv => print!("{}", separated(items, '\t', tsv_cell)),
+        }
+        Ok(())
+    }
+}
+
+/// Left aligned columns separated by two spaces, with the headers of the first item. Empty
+/// cells are shown as `-` so every row has the same number of words.
+pub fn table<T: Tabular>(items: &[T], color: bool) -> String {
+    let Some(first) = items.first() else {
+        return String::new();
+    };
+    let headers: Vec<String> = first.headers().into_iter().map(str::to_uppercase).collect();
+    let rows: Vec<Vec<String>> = items
+        .iter()
+        .map(|item| {
+            item.row()
+                .into_iter()
+                .map(|cell| {
+                    if cell.is_empty() {
+                        "-".to_string()
+                    } else {
+                        cell
+                    }
+                })
+                .collect()
+        })
+        .collect();
+
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.chars().count()).collect();
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let line = |row: &Vec<String>, style: &dyn Fn(usize, String) -> String| {
+        row.iter()
+            .zip(&widths)
+            .enumerate()
+            .map(|(column, (cell, width))| style(column, format!("{cell:<width$}")))
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+    let header_style = |_, cell: String| {
+        if color {
+            cell.bold().to_string()
+        } else {
+            cell
+        }
+    };
+    // The first column is the one to pick from, so it stands out.
+    let row_style = |column, cell: String| {
+        if color && column == 0 {
+            cell.green().to_string()
+        } else {
+            cell
+        }
+    };
+
+    std::iter::once(line(&headers, &header_style))
+        .chain(rows.iter().map(|row| line(row, &row_style)))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Header row and one line per item, ending in a newline.
+fn separated<T: Tabular>(items: &[T], separator: char, escape: fn(&str) -> String) -> String {
+    let Some(first) = items.first() else {
+        return String::new();
+    };
+    std::iter::once(first.headers().into_iter().map(str::to_string).collect())
+        .chain(items.iter().map(Tabular::row))
+        .map(|row: Vec<String>| {
+            let cells: Vec<String> = row.iter().map(|cell| escape(cell)).collect();
+            format!("{}\n", cells.join(&separator.to_string()))
+        })
+        .collect()
+}
+
+/// Quote cells the way RFC 4180 expects.
+fn csv_cell(cell: &str) -> String {
+    if cell.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_string()
+    }
+}
+
+/// TSV has no quoting, so separators inside of cells become spaces.
+fn tsv_cell(cell: &str) -> String {
+    cell.replace(['\t', '\n', '\r'], " ")
+}
+
+/// Fill in `{column}` placeholders from an item's row. `\t` and `\n` are unescaped since
+/// shells pass them through literally.
+pub fn render_template<T: Tabular>(template: &str, item: &T) -> Result<String, MukdukError> {
+    let headers = item.headers();
+    let row = item.row();
+    let template = template.replace("\\t", "\t").replace("\\n", "\n");
+
+    let mut rendered = String::new();
+    let mut rest = template.as_str();
+    while let Some(open) = rest.find('{') {
+        // An unclosed brace is left as it is, along with everything before it.
+        let Some(close) = rest[open..].find('}') else {
+            break;
+        };
+        rendered.push_str(&rest[..open]);
+        let field = &rest[open + 1..open + close];
+        let value = headers
+            .iter()
+            .position(|header| *header == field)
+            .and_then(|column| row.get(column))
+            .ok_or_else(|| MukdukError::UnknownTemplateField {
+                field: field.to_string(),
+                available: headers.join(", "),
+            })?;
+        rendered.push_str(value);
+        rest = &rest[open + close + 1..];
+    }
+    rendered.push_str(rest);
+    Ok(rendered)
+}
+
+/// Size in the largest unit that keeps the number above one, like `du -h`.
+pub fn human_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "K", "M", "G", "T"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes}B")
+    } else {
+        format!("{size:.1}{}", UNITS[unit])
+    }
+}
+
+/// How long ago `then` was, both as unix timestamps, in the largest whole unit.
+pub fn human_age(now: u64, then: u64) -> String {
+    let secs = now.saturating_sub(then);
+    match secs {
+        0..=59 => format!("{secs}s ago"),
+        60..=3_599 => format!("{}m ago", secs / 60),
+        3_600..=86_399 => format!("{}h ago", secs / 3_600),
+        _ => format!("{}d ago", secs / 86_400),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::{human_age, human_bytes, render_template, separated, table, Tabular};
+    use crate::error::MukdukError;
+
+    struct Row(&'static str, &'static str);
+
+    impl Tabular for Row {
+        fn headers(&self) -> Vec<&'static str> {
+            vec!["name", "path"]
+        }
+
+        fn row(&self) -> Vec<String> {
+            vec![self.0.to_string(), self.1.to_string()]
+        }
+    }
+
+    #[test]
+    fn should_align_columns() {
+        assert_eq!(
+            table(&[Row("mukduk", "/p/mukduk"), Row("a", "")], false),
+            "NAME    PATH\nmukduk  /p/mukduk\na       -"
+        );
+        assert_eq!(table::<Row>(&[], false), "");
+    }
+
+    #[test]
+    fn should_escape_separated_values() {
+        let rows = [Row("a,b", "/p/\"q\""), Row("tab\there", "/p")];
+        assert_eq!(
+            separated(&rows, ',', super::csv_cell),
+            "name,path\n\"a,b\",\"/p/\"\"q\"\"\"\ntab\there,/p\n"
+        );
+        assert_eq!(
+            separated(&rows, '\t', super::tsv_cell),
+            "name\tpath\na,b\t/p/\"q\"\ntab here\t/p\n"
+        );
+    }
+
+    #[test]
+    fn should_render_template_fields() -> anyhow::Result<()> {
+        let row = Row("mukduk", "/p/mukduk");
+        assert_eq!(
+            render_template("{name}\\t{path}", &row)?,
+            "mukduk\t/p/mukduk"
+        );
+        assert_eq!(render_template("no fields", &row)?, "no fields");
+        assert_eq!(render_template("a{b", &row)?, "a{b");
+        assert_eq!(render_template("{name} {b", &row)?, "mukduk {b");
+        assert!(matches!(
+            render_template("{size}", &row),
+            Err(MukdukError::UnknownTemplateField { field, .. }) if field == "size"
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn should_humanize_sizes_and_ages() {
+        assert_eq!(human_bytes(512), "512B");
+        assert_eq!(human_bytes(1536), "1.5K");
+        assert_eq!(human_bytes(3 * 1024 * 1024 * 1024), "3.0G");
+        assert_eq!(human_age(100, 70), "30s ago");
+        assert_eq!(human_age(10_000, 0), "2h ago");
+        assert_eq!(human_age(0, 10), "0s ago");
+    }
+}
@@ -2,20 +2,49 @@ use std::{fmt::Display, path::PathBuf};
 
 use serde::Serialize;
 
-#[derive(Debug, Clone, Serialize)]
+use crate::{config::ConfigEnvKey, remote::Remote};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub struct Project {
     pub path: PathBuf,
     pub name: String,
+    pub tags: Vec<String>,
+    /// Shell command every pane of the project's session starts with, instead of a plain shell.
+    /// Backends run it as it is, so it has to start the pane's shell itself.
+    #[serde(skip)]
+    pub command: Option<String>,
 }
 
 impl Project {
-    pub fn new(path: PathBuf, name: String) -> Self {
+    pub const fn new(path: PathBuf, name: String) -> Self {
         Self {
             path,
-            name: name.replace('.', "_"),
+            name,
+            tags: Vec::new(),
+            command: None,
         }
     }
 
+    /// Remote projects are written as `ssh://host/path` in place of a local path.
+    pub fn remote(&self) -> Option<Remote> {
+        Remote::from_path(&self.path)
+    }
+
+    pub fn with_tags(mut self, tags: Vec<String>) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    pub fn with_command(mut self, command: Option<String>) -> Self {
+        self.command = command;
+        self
+    }
+
+    /// Whether the project carries every one of `tags`.
+    pub fn has_tags(&self, tags: &[String]) -> bool {
+        tags.iter().all(|tag| self.tags.contains(tag))
+    }
+
     pub fn get_name(&self) -> String {
         self.name.clone()
     }
@@ -23,6 +52,16 @@ impl Project {
     pub fn get_path(&self) -> PathBuf {
         self.path.clone()
     }
+
+    /// Directory the session is started in. Remote projects start in `$HOME`, since their shell
+    /// changes into the project on the remote host.
+    pub fn session_dir(&self) -> PathBuf {
+        if self.remote().is_some() {
+            PathBuf::try_from(ConfigEnvKey::Home).unwrap_or_else(|_| PathBuf::from("/"))
+        } else {
+            self.get_path()
+        }
+    }
 }
 
 impl Display for Project {
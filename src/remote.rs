@@ -0,0 +1,151 @@
+use std::path::Path;
+
+use crate::shell;
+
+/// A project on another machine, written as `ssh://[user@]host[:port]/path`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Remote {
+    /// `host` or `user@host`, as ssh takes it.
+    pub destination: String,
+    pub port: Option<u16>,
+    /// Absolute path of the project on the remote host.
+    pub path: String,
+}
+
+impl Remote {
+    pub const SCHEME: &'static str = "ssh://";
+
+    /// `None` for anything that is not an `ssh://` uri with a host and a path. IPv6 hosts are
+    /// written in brackets, like `ssh://[::1]:2222/srv/api`.
+    pub fn parse(uri: &str) -> Option<Self> {
+        let rest = uri.strip_prefix(Self::SCHEME)?;
+        let (authority, path) = rest.split_once('/')?;
+        let (user, host_port) = match authority.rsplit_once('@') {
+            Some((user, host_port)) => (Some(user), host_port),
+            None => (None, authority),
+        };
+        let (host, port) = match host_port.strip_prefix('[') {
+            Some(bracketed) => {
+                let (host, port) = bracketed.split_once(']')?;
+                match port {
+                    "" => (host, None),
+                    port => (host, Some(port.strip_prefix(':')?)),
+                }
+            }
+            None => match host_port.rsplit_once(':') {
+                Some((host, port)) => (host, Some(port)),
+                None => (host_port, None),
+            },
+        };
+        let port = match port {
+            Some(port) => Some(port.parse().ok()?),
+            None => None,
+        };
+        // ssh would take a destination starting with `-` as an option.
+        let usable = |part: &str| !part.is_empty() && !part.starts_with('-');
+        if !usable(host) || !user.is_none_or(usable) || path.is_empty() {
+            return None;
+        }
+        Some(Self {
+            destination: user.map_or_else(|| host.to_string(), |user| format!("{user}@{host}")),
+            port,
+            path: format!("/{}", path.trim_end_matches('/')),
+        })
+    }
+
+    pub fn from_path(path: &Path) -> Option<Self> {
+        path.to_str().and_then(Self::parse)
+    }
+
+    /// Shell command that opens a login shell in the project directory on the remote host. The
+    /// remote command is quoted twice, once for the local shell and once for the remote one.
+    pub fn session_command(&self) -> String {
+        let port = self
+            .port
+            .map(|port| format!("-p {port} "))
+            .unwrap_or_default();
+        let remote_command = format!("cd {} && exec $SHELL -l", shell::quote(&self.path));
+        format!(
+            "ssh -t {port}{} {}",
+            shell::quote(&self.destination),
+            shell::quote(&remote_command)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::Remote;
+
+    #[test]
+    fn should_parse_ssh_uris() {
+        assert_eq!(
+            Remote::parse("ssh://me@devbox:2222/home/me/api/"),
+            Some(Remote {
+                destination: "me@devbox".to_string(),
+                port: Some(2222),
+                path: "/home/me/api".to_string(),
+            })
+        );
+        assert_eq!(Remote::parse("/home/me/api"), None);
+        assert_eq!(Remote::parse("ssh://devbox"), None);
+        assert_eq!(Remote::parse("ssh://devbox:ssh/api"), None);
+        assert_eq!(Remote::parse("ssh://-oProxyCommand=x/api"), None);
+        assert_eq!(Remote::parse("ssh://-me@devbox/api"), None);
+    }
+
+    #[test]
+    fn should_parse_ipv6_hosts() {
+        assert_eq!(
+            Remote::parse("ssh://me@[::1]:2222/srv/api"),
+            Some(Remote {
+                destination: "me@::1".to_string(),
+                port: Some(2222),
+                path: "/srv/api".to_string(),
+            })
+        );
+        assert_eq!(
+            Remote::parse("ssh://[fe80::1]/srv/api").map(|r| (r.destination, r.port)),
+            Some(("fe80::1".to_string(), None))
+        );
+        assert_eq!(Remote::parse("ssh://[::1/srv/api"), None);
+    }
+
+    #[test]
+    fn should_cd_into_remote_path() {
+        let remote = Remote::parse("ssh://devbox/srv/api").expect("valid uri");
+        assert_eq!(
+            remote.session_command(),
+            "ssh -t devbox 'cd /srv/api && exec $SHELL -l'"
+        );
+    }
+
+    #[test]
+    fn should_quote_paths_for_both_shells() -> anyhow::Result<()> {
+        let remote = Remote::parse("ssh://devbox:2222/srv/it's $(x) `y` \"z\"").expect("valid uri");
+        let command = remote.session_command();
+        assert!(command.starts_with("ssh -t -p 2222 devbox "), "{command}");
+
+        // Split the command the way the local shell would, then the remote command the way the
+        // remote shell would.
+        let words = |script: &str| -> anyhow::Result<Vec<String>> {
+            let output = std::process::Command::new("sh")
+                .args(["-c", &format!("printf '%s\\n' {script}")])
+                .output()?;
+            Ok(String::from_utf8(output.stdout)?
+                .lines()
+                .map(str::to_string)
+                .collect())
+        };
+        let local = words(command.trim_start_matches("ssh "))?;
+        assert_eq!(local[..4], ["-t", "-p", "2222", "devbox"]);
+        let remote_command = &local[4];
+        let cd = remote_command
+            .strip_suffix(" && exec $SHELL -l")
+            .expect("login shell");
+        assert_eq!(words(cd)?, vec!["cd", "/srv/it's $(x) `y` \"z\""]);
+        Ok(())
+    }
+}
@@ -0,0 +1,155 @@
+use std::{
+    io::Write,
+    process::{Command, Output, Stdio},
+};
+
+use anyhow::Result;
+
+use crate::{
+    error::MukdukError,
+    helper::{spawn_error, wrap_command},
+};
+
+/// Everything that shells out goes through a runner, so backends can be tested without the real
+/// binaries installed.
+pub trait CommandRunner {
+    /// Run a command to completion, capturing its stdout and stderr.
+    fn run(&self, command: &mut Command) -> Result<Output>;
+
+    /// Run a command with `input` written to its stdin, capturing only its stdout. Used for
+    /// interactive programs like fzf that draw their ui on the terminal.
+    fn run_with_input(&self, command: &mut Command, input: &str) -> Result<Output>;
+
+    /// Same as [`CommandRunner::run`], but a non-zero exit status is turned into an error with
+    /// the command's stderr.
+    fn run_checked(&self, command: &mut Command) -> Result<Output> {
+        let output = self.run(command)?;
+        if !output.status.success() {
+            return Err(MukdukError::command_failed(command, &output.stderr).into());
+        }
+        Ok(output)
+    }
+}
+
+/// Runs commands for real.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemRunner;
+
+impl CommandRunner for SystemRunner {
+    fn run(&self, command: &mut Command) -> Result<Output> {
+        wrap_command(command)
+    }
+
+    fn run_with_input(&self, command: &mut Command, input: &str) -> Result<Output> {
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|err| spawn_error(command, err))?;
+
+        // Get the stdin handle of the child process
+        if let Some(stdin) = &mut child.stdin {
+            // Write your input string to the command's stdin
+            stdin.write_all(input.as_bytes())?;
+        } else {
+            log::warn!("Failed to get stdin handle for the child process");
+        }
+
+        // Ensure the child process has finished
+        Ok(child.wait_with_output()?)
+    }
+}
+
+#[cfg(test)]
+pub mod fake {
+    use std::{
+        cell::RefCell,
+        os::unix::process::ExitStatusExt,
+        path::PathBuf,
+        process::{Command, ExitStatus, Output},
+    };
+
+    use anyhow::Result;
+
+    use super::CommandRunner;
+    use crate::error::display_command;
+
+    /// A command the [`FakeRunner`] was asked to run.
+    #[derive(Debug, Clone)]
+    pub struct Call {
+        pub command: String,
+        pub current_dir: Option<PathBuf>,
+        pub input: Option<String>,
+    }
+
+    /// Records every command it is given, and answers with scripted output.
+    ///
+    /// Responses are matched by command prefix (`"tmux has-session -t =muk"`), the first matching
+    /// rule wins. Commands without a matching rule succeed with no output.
+    #[derive(Default)]
+    pub struct FakeRunner {
+        rules: Vec<(String, Output)>,
+        calls: RefCell<Vec<Call>>,
+    }
+
+    impl FakeRunner {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        pub fn on(mut self, prefix: &str, code: i32, stdout: &str, stderr: &str) -> Self {
+            self.rules.push((
+                prefix.to_string(),
+                Output {
+                    status: ExitStatus::from_raw(code << 8),
+                    stdout: stdout.as_bytes().to_vec(),
+                    stderr: stderr.as_bytes().to_vec(),
+                },
+            ));
+            self
+        }
+
+        /// Every command that was run, rendered like it would be typed into a shell.
+        pub fn commands(&self) -> Vec<String> {
+            self.calls
+                .borrow()
+                .iter()
+                .map(|call| call.command.clone())
+                .collect()
+        }
+
+        pub fn calls(&self) -> Vec<Call> {
+            self.calls.borrow().clone()
+        }
+
+        fn respond(&self, command: &Command, input: Option<&str>) -> Output {
+            let rendered = display_command(command);
+            self.calls.borrow_mut().push(Call {
+                command: rendered.clone(),
+                current_dir: command.get_current_dir().map(PathBuf::from),
+                input: input.map(str::to_string),
+            });
+            self.rules
+                .iter()
+                .find(|(prefix, _)| rendered.starts_with(prefix))
+                .map_or_else(
+                    || Output {
+                        status: ExitStatus::from_raw(0),
+                        stdout: Vec::new(),
+                        stderr: Vec::new(),
+                    },
+                    |(_, output)| output.clone(),
+                )
+        }
+    }
+
+    impl CommandRunner for FakeRunner {
+        fn run(&self, command: &mut Command) -> Result<Output> {
+            Ok(self.respond(command, None))
+        }
+
+        fn run_with_input(&self, command: &mut Command, input: &str) -> Result<Output> {
+            Ok(self.respond(command, Some(input)))
+        }
+    }
+}
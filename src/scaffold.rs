@@ -0,0 +1,128 @@
+use anyhow::Result;
+use std::{fs, path::Path};
+
+use crate::error::MukdukError;
+
+/// Copy `template` to `destination`, replacing every `{{key}}` from `vars` in file contents and
+/// `{{name}}` in file names. Files that are not utf8 are copied as they are.
+pub fn scaffold(template: &Path, destination: &Path, vars: &[(&str, &str)]) -> Result<()> {
+    if destination.exists() {
+        return Err(MukdukError::AlreadyExists(destination.to_path_buf()).into());
+    }
+    // Never leave a half copied project behind.
+    copy_rendered(template, destination, vars).inspect_err(|_| {
+        if let Err(err) = fs::remove_dir_all(destination) {
+            log::warn!("could not clean up {destination:?}: {err}");
+        }
+    })
+}
+
+fn copy_rendered(from: &Path, to: &Path, vars: &[(&str, &str)]) -> Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        // Other vars like `{{path}}` hold whole paths, which would put files outside of `to`.
+        let file_name = substitute(
+            &entry.file_name().to_string_lossy(),
+            &vars
+                .iter()
+                .copied()
+                .filter(|(key, _)| *key == "name")
+                .collect::<Vec<_>>(),
+        );
+        let target = to.join(file_name);
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            copy_rendered(&entry.path(), &target, vars)?;
+            continue;
+        }
+        // Links are kept as links, so they can not pull in files from outside of the template.
+        if file_type.is_symlink() {
+            std::os::unix::fs::symlink(fs::read_link(entry.path())?, &target)?;
+            continue;
+        }
+        let bytes = fs::read(entry.path())?;
+        match String::from_utf8(bytes) {
+            Ok(contents) => fs::write(&target, substitute(&contents, vars))?,
+            Err(err) => fs::write(&target, err.into_bytes())?,
+        }
+        fs::set_permissions(&target, entry.metadata()?.permissions())?;
+    }
+    Ok(())
+}
+
+fn substitute(text: &str, vars: &[(&str, &str)]) -> String {
+    vars.iter().fold(text.to_string(), |text, (key, value)| {
+        text.replace(&format!("{{{{{key}}}}}"), value)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use pretty_assertions::assert_eq;
+
+    use super::scaffold;
+
+    #[test]
+    fn should_substitute_names_and_contents() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let template = dir.path().join("template");
+        fs::create_dir_all(template.join("src"))?;
+        fs::write(
+            template.join("Cargo.toml"),
+            "[package]\nname = \"{{name}}\"\n",
+        )?;
+        fs::write(template.join("src").join("{{name}}.rs"), "// {{other}}\n")?;
+        fs::write(template.join("{{path}}.md"), "{{path}}\n")?;
+        fs::write(template.join("logo.bin"), [0xff, 0xfe, 0x00])?;
+
+        let destination = dir.path().join("demo");
+        scaffold(
+            &template,
+            &destination,
+            &[("name", "demo"), ("path", "/tmp/demo")],
+        )?;
+
+        assert_eq!(
+            fs::read_to_string(destination.join("Cargo.toml"))?,
+            "[package]\nname = \"demo\"\n"
+        );
+        assert_eq!(
+            fs::read_to_string(destination.join("src").join("demo.rs"))?,
+            "// {{other}}\n"
+        );
+        assert_eq!(
+            fs::read_to_string(destination.join("{{path}}.md"))?,
+            "/tmp/demo\n"
+        );
+        assert_eq!(
+            fs::read(destination.join("logo.bin"))?,
+            vec![0xff, 0xfe, 0x00]
+        );
+        assert!(scaffold(&template, &destination, &[]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn should_keep_links_and_clean_up_failed_copies() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let template = dir.path().join("template");
+        fs::create_dir_all(&template)?;
+        fs::write(dir.path().join("secret"), "outside")?;
+        std::os::unix::fs::symlink("../secret", template.join("link"))?;
+
+        let destination = dir.path().join("demo");
+        scaffold(&template, &destination, &[])?;
+        assert_eq!(
+            fs::read_link(destination.join("link"))?,
+            std::path::PathBuf::from("../secret")
+        );
+
+        let failed = dir.path().join("failed");
+        assert!(scaffold(&dir.path().join("missing"), &failed, &[]).is_err());
+        assert!(!failed.exists());
+        Ok(())
+    }
+}
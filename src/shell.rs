@@ -0,0 +1,23 @@
+/// Quote `arg` for a posix shell, leaving plain words like `fzf` or `--multi` as they are.
+pub fn quote(arg: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "-_./=,%:@+".contains(c);
+    if !arg.is_empty() && arg.chars().all(plain) {
+        return arg.to_string();
+    }
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::quote;
+
+    #[test]
+    fn should_only_quote_when_needed() {
+        assert_eq!(quote("--tabstop=8"), "--tabstop=8");
+        assert_eq!(quote(""), "''");
+        assert_eq!(quote("a b"), "'a b'");
+        assert_eq!(quote("it's $HOME `x`"), r"'it'\''s $HOME `x`'");
+    }
+}
@@ -0,0 +1,186 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::{config::ConfigEnvKey, remote::Remote, runner::CommandRunner};
+
+/// Somewhere other than the projects dir that project directories are read from. Any of them can
+/// also list remote projects as `ssh://host/path`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ProjectSource {
+    /// Every directory in zoxide's database, from `zoxide query -l`.
+    Zoxide,
+    /// A file with one directory per line. Blank lines and lines starting with `#` are skipped.
+    File { path: PathBuf },
+    /// A shell command printing one directory per line.
+    Command { command: String },
+}
+
+impl ProjectSource {
+    /// Directories listed by this source, which may not exist.
+    pub fn directories(&self, runner: &dyn CommandRunner) -> Result<Vec<PathBuf>> {
+        let (listing, relative_to) = match self {
+            Self::Zoxide => (
+                Self::stdout(runner, Command::new("zoxide").args(["query", "-l"]))?,
+                None,
+            ),
+            Self::File { path } => {
+                let path = expand_home(path);
+                (
+                    fs::read_to_string(&path)?,
+                    path.parent().map(Path::to_path_buf),
+                )
+            }
+            Self::Command { command } => (
+                Self::stdout(runner, Command::new("sh").args(["-c", command]))?,
+                None,
+            ),
+        };
+        Ok(listing
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let dir = expand_home(Path::new(line));
+                match &relative_to {
+                    Some(base) if dir.is_relative() && Remote::parse(line).is_none() => {
+                        base.join(dir)
+                    }
+                    _ => dir,
+                }
+            })
+            .collect())
+    }
+
+    fn stdout(runner: &dyn CommandRunner, command: &mut Command) -> Result<String> {
+        let output = runner.run_checked(command)?;
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+}
+
+fn expand_home(path: &Path) -> PathBuf {
+    match (
+        path.strip_prefix("~"),
+        PathBuf::try_from(ConfigEnvKey::Home),
+    ) {
+        (Ok(rest), Ok(home)) => home.join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Merge `dirs` with every source's directories, keeping the first of any that resolve to the
+/// same place. Directories that do not exist are dropped, and so are sources that fail.
+pub fn merge_sources(
+    dirs: Vec<PathBuf>,
+    sources: &[ProjectSource],
+    runner: &dyn CommandRunner,
+) -> Vec<PathBuf> {
+    let from_sources = sources.iter().flat_map(|source| {
+        source.directories(runner).unwrap_or_else(|err| {
+            log::warn!("skipping project source {source:?}: {err}");
+            Vec::new()
+        })
+    });
+
+    let mut seen = HashSet::new();
+    dirs.into_iter()
+        .chain(from_sources)
+        .filter(|dir| dir.is_dir() || Remote::from_path(dir).is_some())
+        .filter(|dir| seen.insert(fs::canonicalize(dir).unwrap_or_else(|_| dir.clone())))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use pretty_assertions::assert_eq;
+
+    use super::{merge_sources, ProjectSource};
+    use crate::runner::fake::FakeRunner;
+
+    #[test]
+    fn should_merge_sources_without_duplicates() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        for name in ["a", "b", "c", "d"] {
+            fs::create_dir(dir.path().join(name))?;
+        }
+        let path = |name: &str| dir.path().join(name);
+        let list = dir.path().join("projects.txt");
+        fs::write(&list, "# mine\nb\n\n../missing\nssh://devbox/srv/api\n")?;
+        let runner = FakeRunner::new()
+            .on(
+                "zoxide query -l",
+                0,
+                &format!(
+                    "{}\n{}/../c\n",
+                    path("a").to_string_lossy(),
+                    dir.path().join("a").to_string_lossy()
+                ),
+                "",
+            )
+            .on(
+                "sh -c",
+                0,
+                &format!("{}\n", path("d").to_string_lossy()),
+                "",
+            );
+
+        let merged = merge_sources(
+            vec![path("a")],
+            &[
+                ProjectSource::Zoxide,
+                ProjectSource::File { path: list },
+                ProjectSource::Command {
+                    command: "ghq list -p".to_string(),
+                },
+            ],
+            &runner,
+        );
+
+        assert_eq!(
+            merged,
+            vec![
+                path("a"),
+                dir.path().join("a").join("..").join("c"),
+                path("b"),
+                PathBuf::from("ssh://devbox/srv/api"),
+                path("d"),
+            ]
+        );
+        assert_eq!(
+            runner.commands(),
+            vec!["zoxide query -l", "sh -c ghq list -p"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn should_skip_failing_sources() {
+        let runner = FakeRunner::new().on("zoxide", 127, "", "zoxide: not found");
+        assert!(merge_sources(Vec::new(), &[ProjectSource::Zoxide], &runner).is_empty());
+    }
+
+    #[test]
+    fn should_parse_sources_from_config() -> anyhow::Result<()> {
+        let config = crate::config::ConfigFormat::Toml.parse(
+            "[[sources]]\ntype = \"zoxide\"\n\n[[sources]]\ntype = \"command\"\ncommand = \"ghq list -p\"\n",
+        )?;
+        assert_eq!(
+            config.sources,
+            vec![
+                ProjectSource::Zoxide,
+                ProjectSource::Command {
+                    command: "ghq list -p".to_string()
+                }
+            ]
+        );
+        Ok(())
+    }
+}
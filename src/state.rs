@@ -0,0 +1,295 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    cli::MukdukContext,
+    config::ConfigEnvKey,
+    multiplexer::{BackendOptions, Multiplexers},
+    project::Project,
+};
+
+/// How many sessions are remembered, enough to toggle between the last two.
+const RECENT_SESSIONS: usize = 2;
+
+/// A session mukduk switched to or away from.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SessionRef {
+    pub multiplexer: Multiplexers,
+    pub name: String,
+    /// Project the session was opened from, so it can be opened again after it was killed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+}
+
+impl SessionRef {
+    /// Whether both refer to the same session, wherever it was opened from.
+    fn same_session(&self, other: &Self) -> bool {
+        self.multiplexer == other.multiplexer && self.name == other.name
+    }
+}
+
+/// What mukduk remembers between runs, kept in `$XDG_STATE_HOME/mukduk/state.json`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct State {
+    /// Sessions most recently switched between, newest first.
+    #[serde(default)]
+    pub recent: Vec<SessionRef>,
+}
+
+impl State {
+    pub fn path() -> Result<PathBuf> {
+        Ok(PathBuf::try_from(ConfigEnvKey::XDGState)?
+            .join("mukduk")
+            .join("state.json"))
+    }
+
+    /// A missing or unreadable state file is treated as empty, it only holds conveniences.
+    pub fn load(path: &Path) -> Self {
+        let Ok(contents) = fs::read_to_string(path) else {
+            return Self::default();
+        };
+        serde_json::from_str(&contents).unwrap_or_else(|err| {
+            log::warn!("ignoring broken state file {path:?}: {err}");
+            Self::default()
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Make `session` the most recent one, keeping the path it was opened from when known.
+    pub fn record(&mut self, mut session: SessionRef) {
+        if let Some(index) = self.recent.iter().position(|r| r.same_session(&session)) {
+            let known = self.recent.remove(index);
+            session.path = session.path.or(known.path);
+        }
+        self.recent.insert(0, session);
+        self.recent.truncate(RECENT_SESSIONS);
+    }
+
+    /// Most recent session that is not `current`.
+    pub fn previous(&self, current: Option<&SessionRef>) -> Option<&SessionRef> {
+        self.recent
+            .iter()
+            .find(|recent| current.is_none_or(|current| !recent.same_session(current)))
+    }
+
+    /// Follow a session that was renamed.
+    pub fn rename_session(&mut self, multiplexer: Multiplexers, from: &str, to: &str) {
+        for recent in &mut self.recent {
+            if recent.multiplexer == multiplexer && recent.name == from {
+                recent.name = to.to_string();
+            }
+        }
+    }
+
+    /// Follow a project directory that was moved.
+    pub fn move_path(&mut self, from: &Path, to: &Path) {
+        for recent in &mut self.recent {
+            if recent.path.as_deref() == Some(from) {
+                recent.path = Some(to.to_path_buf());
+            }
+        }
+    }
+
+    /// Forget sessions opened from a project directory that was archived or deleted.
+    pub fn forget_path(&mut self, path: &Path) {
+        self.recent
+            .retain(|recent| recent.path.as_deref() != Some(path));
+    }
+}
+
+/// Load, change, and save the state file. Failing to is only logged, since the state should never
+/// stop a command from doing its job.
+pub fn update_state(change: impl FnOnce(&mut State)) {
+    let result = State::path().and_then(|path| {
+        let mut state = State::load(&path);
+        change(&mut state);
+        state.save(&path)
+    });
+    if let Err(err) = result {
+        log::warn!("could not save mukduk state: {err}");
+    }
+}
+
+/// Session mukduk is running inside of, if any.
+pub fn current_session(context: &MukdukContext) -> Option<SessionRef> {
+    let multiplexer = Multiplexers::current()?;
+    let backend = multiplexer.backend(BackendOptions {
+        tmux_socket: context.tmux_socket.clone(),
+        ..Default::default()
+    });
+    match backend.current_session() {
+        Ok(name) => name.map(|name| SessionRef {
+            multiplexer,
+            name,
+            path: None,
+        }),
+        Err(err) => {
+            log::debug!("could not read the current session: {err}");
+            None
+        }
+    }
+}
+
+/// Remember switching from the current session to `to`.
+fn record_switch(context: &MukdukContext, to: SessionRef) {
+    let current = current_session(context);
+    update_state(|state| {
+        if let Some(current) = current {
+            state.record(current);
+        }
+        state.record(to);
+    });
+}
+
+/// Open `project` with `open`, remembering the switch to its session unless it is opened in the
+/// background. Attaching only returns once the user detaches, so the switch is remembered first
+/// and forgotten again when opening fails.
+pub fn open_recorded(
+    context: &MukdukContext,
+    multiplexer: Multiplexers,
+    detach: bool,
+    project: Project,
+    open: impl FnOnce(Project) -> Result<()>,
+) -> Result<()> {
+    if detach {
+        return open(project);
+    }
+    let before = State::path().map(|path| State::load(&path));
+    record_switch(
+        context,
+        SessionRef {
+            multiplexer,
+            name: context
+                .config
+                .sanitize_policy(multiplexer)
+                .apply(&project.name),
+            path: Some(project.path.clone()),
+        },
+    );
+    let result = open(project);
+    if result.is_err() {
+        if let Ok(before) = before {
+            update_state(|state| *state = before);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use pretty_assertions::assert_eq;
+
+    use super::{SessionRef, State};
+    use crate::multiplexer::Multiplexers;
+
+    fn session(multiplexer: Multiplexers, name: &str) -> SessionRef {
+        SessionRef {
+            multiplexer,
+            name: name.to_string(),
+            path: None,
+        }
+    }
+
+    #[test]
+    fn should_toggle_between_last_two_sessions() {
+        let mut state = State::default();
+        state.record(session(Multiplexers::Tmux, "api"));
+        state.record(session(Multiplexers::Zellij, "web"));
+        state.record(session(Multiplexers::Tmux, "docs"));
+        assert_eq!(
+            state.recent,
+            vec![
+                session(Multiplexers::Tmux, "docs"),
+                session(Multiplexers::Zellij, "web")
+            ]
+        );
+
+        let docs = session(Multiplexers::Tmux, "docs");
+        assert_eq!(
+            state.previous(Some(&docs)),
+            Some(&session(Multiplexers::Zellij, "web"))
+        );
+        state.record(session(Multiplexers::Zellij, "web"));
+        assert_eq!(
+            state.previous(Some(&session(Multiplexers::Zellij, "web"))),
+            Some(&docs)
+        );
+        assert_eq!(
+            state.previous(None),
+            Some(&session(Multiplexers::Zellij, "web"))
+        );
+    }
+
+    #[test]
+    fn should_follow_renamed_sessions_and_moved_projects() {
+        let mut state = State::default();
+        state.record(SessionRef {
+            path: Some(PathBuf::from("/p/api")),
+            ..session(Multiplexers::Tmux, "api")
+        });
+        state.record(session(Multiplexers::Zellij, "api"));
+
+        state.rename_session(Multiplexers::Tmux, "api", "api-v2");
+        state.move_path(&PathBuf::from("/p/api"), &PathBuf::from("/p/api-v2"));
+
+        assert_eq!(
+            state.recent,
+            vec![
+                session(Multiplexers::Zellij, "api"),
+                SessionRef {
+                    path: Some(PathBuf::from("/p/api-v2")),
+                    ..session(Multiplexers::Tmux, "api-v2")
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn should_forget_sessions_of_removed_projects() {
+        let mut state = State::default();
+        state.record(SessionRef {
+            path: Some(PathBuf::from("/p/api")),
+            ..session(Multiplexers::Tmux, "api")
+        });
+        state.record(session(Multiplexers::Tmux, "notes"));
+
+        state.forget_path(&PathBuf::from("/p/api"));
+
+        assert_eq!(state.recent, vec![session(Multiplexers::Tmux, "notes")]);
+    }
+
+    #[test]
+    fn should_save_and_load_state() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("mukduk").join("state.json");
+        assert_eq!(State::load(&path), State::default());
+
+        let mut state = State::default();
+        state.record(SessionRef {
+            path: Some(PathBuf::from("/p/api")),
+            ..session(Multiplexers::Tmux, "api")
+        });
+        state.save(&path)?;
+        assert_eq!(State::load(&path), state);
+
+        state.record(session(Multiplexers::Tmux, "api"));
+        assert_eq!(state.recent[0].path, Some(PathBuf::from("/p/api")));
+
+        std::fs::write(&path, "{")?;
+        assert_eq!(State::load(&path), State::default());
+        Ok(())
+    }
+}
@@ -0,0 +1,63 @@
+//! Shell completion, driven the same way the registered scripts call back into `mukduk`.
+
+use std::{fs, process::Command};
+
+use pretty_assertions::assert_eq;
+
+fn complete(projects_dir: &std::path::Path, words: &[&str]) -> Vec<String> {
+    let home = projects_dir.join("home");
+    let output = Command::new(env!("CARGO_BIN_EXE_mukduk"))
+        .env("COMPLETE", "fish")
+        .env("PROJECTS_DIR", projects_dir)
+        .env("HOME", &home)
+        .env("XDG_CONFIG_HOME", home.join(".config"))
+        .arg("--")
+        .args(words)
+        .output()
+        .expect("mukduk should run");
+    assert!(output.status.success());
+
+    // fish candidates are `value\thelp`, one per line.
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| line.split('\t').next().unwrap_or_default().to_string())
+        .collect()
+}
+
+#[test]
+fn completes_project_names_and_dirs() {
+    let projects_dir = tempfile::tempdir().expect("temp dir should be created");
+    fs::create_dir(projects_dir.path().join("mukduk")).expect("project should be created");
+    fs::create_dir(projects_dir.path().join("dotfiles")).expect("project should be created");
+
+    let mut names = complete(
+        projects_dir.path(),
+        &["mukduk", "project", "open", "--name", ""],
+    );
+    names.sort();
+    assert_eq!(names, vec!["dotfiles", "mukduk"]);
+
+    let mukduk = projects_dir.path().join("mukduk");
+    let prefix = format!("{}/muk", projects_dir.path().to_string_lossy());
+    let dirs = complete(
+        projects_dir.path(),
+        &["mukduk", "project", "open", "--project-dir", &prefix],
+    );
+    assert_eq!(dirs, vec![mukduk.to_string_lossy().to_string()]);
+}
+
+#[test]
+fn prints_registration_script_for_every_shell() {
+    let home = tempfile::tempdir().expect("temp dir should be created");
+    for shell in ["bash", "zsh", "fish", "elvish", "powershell"] {
+        let output = Command::new(env!("CARGO_BIN_EXE_mukduk"))
+            .env("HOME", home.path())
+            .env("XDG_CONFIG_HOME", home.path().join(".config"))
+            .args(["completions", shell])
+            .output()
+            .expect("mukduk should run");
+
+        assert!(output.status.success(), "{shell} failed");
+        assert!(String::from_utf8_lossy(&output.stdout).contains("COMPLETE"));
+    }
+}
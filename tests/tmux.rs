@@ -0,0 +1,587 @@
+//! End-to-end tests running the `mukduk` binary against an isolated, headless tmux server.
+//!
+//! Every test gets its own `TMUX_TMPDIR` and socket name, so nothing touches the tmux server
+//! of the user running the tests. Tests are skipped when tmux is not installed.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+
+use pretty_assertions::assert_eq;
+use tempfile::TempDir;
+
+const SOCKET: &str = "mukduk-test";
+
+struct TmuxServer {
+    dir: TempDir,
+}
+
+impl TmuxServer {
+    fn start() -> Option<Self> {
+        if Command::new("tmux").arg("-V").output().is_err() {
+            eprintln!("tmux is not installed, skipping.");
+            return None;
+        }
+        let dir = tempfile::tempdir().expect("temp dir should be created");
+        fs::create_dir_all(dir.path().join("home")).expect("home should be created");
+        fs::create_dir_all(dir.path().join("projects")).expect("projects should be created");
+        Some(Self { dir })
+    }
+
+    fn home(&self) -> PathBuf {
+        self.dir.path().join("home")
+    }
+
+    fn project(&self, name: &str) -> PathBuf {
+        let path = self.dir.path().join("projects").join(name);
+        fs::create_dir_all(&path).expect("project should be created");
+        path
+    }
+
+    /// Scripts written here shadow real binaries for mukduk and the tmux server.
+    fn bin(&self) -> PathBuf {
+        self.dir.path().join("bin")
+    }
+
+    fn env(&self, command: &mut Command) {
+        let path = std::env::var("PATH").unwrap_or_default();
+        command
+            .env("PATH", format!("{}:{path}", self.bin().to_string_lossy()))
+            .env_remove("TMUX")
+            .env_remove("XDG_CONFIG_HOME")
+            .env_remove("XDG_STATE_HOME")
+            .env("HOME", self.home())
+            .env("TMUX_TMPDIR", self.dir.path())
+            .env_remove("MUKDUK_TMUX_SOCKET")
+            .env("PROJECTS_DIR", self.dir.path().join("projects"))
+            .env("SHELL", "/bin/sh");
+    }
+
+    fn mukduk(&self, args: &[&str]) -> Output {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_mukduk"));
+        self.env(&mut command);
+        command
+            .env("MUKDUK_TMUX_SOCKET", SOCKET)
+            .args(args)
+            .output()
+            .expect("mukduk should run")
+    }
+
+    fn tmux(&self, args: &[&str]) -> Output {
+        let mut command = Command::new("tmux");
+        self.env(&mut command);
+        command
+            .args(["-L", SOCKET])
+            .args(args)
+            .output()
+            .expect("tmux should run")
+    }
+
+    /// `(session_name, session_path)` of every live session, sorted by name.
+    fn sessions(&self) -> Vec<(String, PathBuf)> {
+        // tmux escapes tabs when not writing to a terminal, and forbids `:` in session names.
+        let output = self.tmux(&["ls", "-F", "#{session_name}:#{session_path}"]);
+        let mut sessions: Vec<_> = String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, path)| (name.to_string(), PathBuf::from(path)))
+            .collect();
+        sessions.sort();
+        sessions
+    }
+
+    fn session_names(&self) -> Vec<String> {
+        self.sessions().into_iter().map(|(name, _)| name).collect()
+    }
+}
+
+impl Drop for TmuxServer {
+    fn drop(&mut self) {
+        self.tmux(&["kill-server"]);
+    }
+}
+
+fn assert_success(output: &Output) {
+    assert!(
+        output.status.success(),
+        "mukduk failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).expect("path should exist")
+}
+
+#[test]
+fn project_open_creates_session_in_project_dir() {
+    let Some(server) = TmuxServer::start() else {
+        return;
+    };
+    let project = server.project("mukduk");
+
+    assert_success(&server.mukduk(&[
+        "project",
+        "open",
+        "-m",
+        "tmux",
+        "-d",
+        "-p",
+        project.to_str().expect("utf8 path"),
+    ]));
+
+    let sessions = server.sessions();
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].0, "mukduk");
+    assert_eq!(canonical(&sessions[0].1), canonical(&project));
+}
+
+#[test]
+fn project_open_sanitizes_dotted_names() {
+    let Some(server) = TmuxServer::start() else {
+        return;
+    };
+    let project = server.project("my.app");
+
+    assert_success(&server.mukduk(&[
+        "project",
+        "open",
+        "-m",
+        "tmux",
+        "-d",
+        "-p",
+        project.to_str().expect("utf8 path"),
+    ]));
+
+    assert_eq!(server.session_names(), vec!["my_app"]);
+}
+
+#[test]
+fn project_open_query_skips_picker_for_clear_match() {
+    let Some(server) = TmuxServer::start() else {
+        return;
+    };
+    server.project("mukduk");
+    server.project("git_lib");
+
+    assert_success(&server.mukduk(&["project", "open", "muk", "-m", "tmux", "-d"]));
+
+    assert_eq!(server.session_names(), vec!["mukduk"]);
+}
+
+#[test]
+fn project_open_twice_reuses_session() {
+    let Some(server) = TmuxServer::start() else {
+        return;
+    };
+    let project = server.project("mukduk");
+    let args = [
+        "project",
+        "open",
+        "-m",
+        "tmux",
+        "-d",
+        "-n",
+        "muk",
+        "-p",
+        project.to_str().expect("utf8 path"),
+    ];
+
+    assert_success(&server.mukduk(&args));
+    assert_success(&server.mukduk(&args));
+
+    assert_eq!(server.session_names(), vec!["muk"]);
+}
+
+#[test]
+fn scratch_opens_in_home() {
+    let Some(server) = TmuxServer::start() else {
+        return;
+    };
+
+    assert_success(&server.mukduk(&["project", "scratch", "-m", "tmux", "-d"]));
+
+    let sessions = server.sessions();
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].0, "scratch");
+    assert_eq!(canonical(&sessions[0].1), canonical(&server.home()));
+}
+
+#[test]
+fn home_increments_unique_session_names() {
+    let Some(server) = TmuxServer::start() else {
+        return;
+    };
+
+    assert_success(&server.mukduk(&["project", "home", "-m", "tmux", "-d"]));
+    assert_success(&server.mukduk(&["project", "home", "-m", "tmux", "-d"]));
+
+    assert_eq!(server.session_names(), vec!["0", "1"]);
+}
+
+#[test]
+fn kill_removes_only_named_session() {
+    let Some(server) = TmuxServer::start() else {
+        return;
+    };
+    assert_success(&server.mukduk(&["project", "scratch", "-m", "tmux", "-d"]));
+    assert_success(&server.mukduk(&["project", "scratch", "-m", "tmux", "-d", "-n", "other"]));
+
+    assert_success(&server.mukduk(&["project", "kill", "-m", "tmux", "-n", "scratch"]));
+
+    assert_eq!(server.session_names(), vec!["other"]);
+}
+
+#[test]
+fn kill_keeps_dirty_project_sessions_without_force() {
+    let Some(server) = TmuxServer::start() else {
+        return;
+    };
+    let project = server.project("dirty");
+    let init = Command::new("git")
+        .args(["init", "-q"])
+        .current_dir(&project)
+        .status()
+        .expect("git should run");
+    assert!(init.success());
+    fs::write(project.join("notes.md"), "wip").expect("file should be written");
+    let open = [
+        "project",
+        "open",
+        "-m",
+        "tmux",
+        "-d",
+        "-p",
+        project.to_str().expect("utf8 path"),
+    ];
+    assert_success(&server.mukduk(&open));
+
+    let output = server.mukduk(&["project", "kill", "-m", "tmux", "-n", "dirty"]);
+    assert_success(&output);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("uncommitted changes"));
+    assert_eq!(server.session_names(), vec!["dirty"]);
+
+    assert_success(&server.mukduk(&["project", "kill", "-m", "tmux", "-n", "dirty", "--force"]));
+    assert_eq!(server.session_names(), Vec::<String>::new());
+}
+
+#[test]
+fn archive_kills_session_and_restore_moves_project_back() {
+    let Some(server) = TmuxServer::start() else {
+        return;
+    };
+    let project = server.project("old");
+    let archive = server.dir.path().join("archive");
+    let config = server.dir.path().join("config.toml");
+    fs::write(
+        &config,
+        format!("archive_dir = {:?}\n", archive.to_str().expect("utf8 path")),
+    )
+    .expect("config should be written");
+    let config = config.to_str().expect("utf8 path");
+    assert_success(&server.mukduk(&[
+        "project",
+        "open",
+        "-m",
+        "tmux",
+        "-d",
+        "-p",
+        project.to_str().expect("utf8 path"),
+    ]));
+
+    assert_success(&server.mukduk(&["-c", config, "project", "archive", "old"]));
+    assert_eq!(server.session_names(), Vec::<String>::new());
+    assert!(!project.exists());
+    assert!(archive.join("old").is_dir());
+
+    assert_success(&server.mukduk(&["-c", config, "project", "restore", "old"]));
+    assert!(project.is_dir());
+    assert!(!archive.join("old").exists());
+
+    let outside = server.dir.path().join("x");
+    fs::create_dir_all(&outside).expect("dir should be created");
+    let output = server.mukduk(&["-c", config, "project", "restore", "../x"]);
+    assert!(!output.status.success());
+    assert!(outside.is_dir());
+}
+
+#[test]
+fn session_rename_renames_live_session() {
+    let Some(server) = TmuxServer::start() else {
+        return;
+    };
+    assert_success(&server.mukduk(&["project", "scratch", "-m", "tmux", "-d"]));
+
+    assert_success(&server.mukduk(&["session", "rename", "scratch", "notes.md"]));
+    assert_eq!(server.session_names(), vec!["notes_md"]);
+
+    let output = server.mukduk(&["session", "rename", "scratch", "other"]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn project_rename_moves_dir_and_session() {
+    let Some(server) = TmuxServer::start() else {
+        return;
+    };
+    let project = server.project("api.v1");
+    assert_success(&server.mukduk(&["project", "open", "api.v1", "-m", "tmux", "-d"]));
+    assert_eq!(server.session_names(), vec!["api_v1"]);
+
+    assert_success(&server.mukduk(&["project", "rename", "api.v1", "api.v2"]));
+    assert!(!project.exists());
+    assert!(server.dir.path().join("projects").join("api.v2").is_dir());
+    assert_eq!(server.session_names(), vec!["api_v2"]);
+
+    let output = server.mukduk(&["project", "rename", "api.v2", "../escaped"]);
+    assert!(!output.status.success());
+    assert!(server.dir.path().join("projects").join("api.v2").is_dir());
+    assert!(!server.dir.path().join("escaped").exists());
+}
+
+#[test]
+fn project_open_runs_ssh_for_remote_projects() {
+    let Some(server) = TmuxServer::start() else {
+        return;
+    };
+    // Stands in for ssh, recording how it was called and keeping the pane alive.
+    fs::create_dir_all(server.bin()).expect("bin should be created");
+    let ssh = server.bin().join("ssh");
+    fs::write(
+        &ssh,
+        "#!/bin/sh\necho \"$@\" > \"$HOME/ssh-args\"\nexec sleep 60\n",
+    )
+    .expect("ssh should be written");
+    fs::set_permissions(&ssh, std::os::unix::fs::PermissionsExt::from_mode(0o755))
+        .expect("ssh should be executable");
+
+    assert_success(&server.mukduk(&[
+        "project",
+        "open",
+        "-m",
+        "tmux",
+        "-d",
+        "-p",
+        "ssh://devbox/srv/api",
+    ]));
+    assert_eq!(server.session_names(), vec!["api"]);
+
+    let option = server.tmux(&["show-options", "-v", "-t", "api", "default-command"]);
+    assert_eq!(
+        String::from_utf8_lossy(&option.stdout).trim(),
+        "ssh -t devbox 'cd /srv/api && exec $SHELL -l'"
+    );
+    let ssh_args = server.home().join("ssh-args");
+    for _ in 0..50 {
+        if ssh_args.exists() {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    }
+    assert_eq!(
+        fs::read_to_string(ssh_args).expect("ssh should have run"),
+        "-t devbox cd /srv/api && exec $SHELL -l\n"
+    );
+}
+
+#[test]
+fn project_open_container_execs_into_compose_service() {
+    let Some(server) = TmuxServer::start() else {
+        return;
+    };
+    // Stands in for docker, keeping the pane alive.
+    fs::create_dir_all(server.bin()).expect("bin should be created");
+    let docker = server.bin().join("docker");
+    fs::write(&docker, "#!/bin/sh\nexec sleep 60\n").expect("docker should be written");
+    fs::set_permissions(&docker, std::os::unix::fs::PermissionsExt::from_mode(0o755))
+        .expect("docker should be executable");
+    let shop = server.project("shop");
+    fs::write(shop.join("compose.yaml"), "services:\n  app: {}\n")
+        .expect("compose file should be written");
+
+    assert_success(&server.mukduk(&["project", "open", "-m", "tmux", "-d", "--container", "shop"]));
+    assert_eq!(server.session_names(), vec!["shop"]);
+
+    let option = server.tmux(&["show-options", "-v", "-t", "shop", "default-command"]);
+    assert_eq!(
+        String::from_utf8_lossy(&option.stdout).trim(),
+        "docker compose up -d app && exec docker compose exec app sh"
+    );
+}
+
+#[test]
+fn project_open_loads_direnv_environment() {
+    let Some(server) = TmuxServer::start() else {
+        return;
+    };
+    // Stands in for direnv, keeping the pane alive.
+    fs::create_dir_all(server.bin()).expect("bin should be created");
+    let direnv = server.bin().join("direnv");
+    fs::write(&direnv, "#!/bin/sh\nexec sleep 60\n").expect("direnv should be written");
+    fs::set_permissions(&direnv, std::os::unix::fs::PermissionsExt::from_mode(0o755))
+        .expect("direnv should be executable");
+    let tools = server.project("tools");
+    fs::write(tools.join(".envrc"), "use flake\n").expect("envrc should be written");
+    let config = server.dir.path().join("config.toml");
+    fs::write(&config, "[projects.tools]\nenv = \"auto\"\n").expect("config should be written");
+    let config = config.to_str().expect("utf8 path");
+    // A checked in settings file must not get a repo's `.envrc` run without `direnv allow`.
+    let cloned = server.project("cloned");
+    fs::write(cloned.join(".envrc"), "use flake\n").expect("envrc should be written");
+    fs::write(cloned.join(".mukduk.toml"), "env = \"auto\"\n").expect("settings should be written");
+
+    let listed = server.mukduk(&["-c", config, "project", "list", "--output", "json"]);
+    assert_success(&listed);
+    let listed = String::from_utf8_lossy(&listed.stdout);
+    assert!(
+        listed.contains("tools") && !listed.contains("direnv"),
+        "{listed}"
+    );
+
+    assert_success(&server.mukduk(&["-c", config, "project", "open", "-m", "tmux", "-d", "tools"]));
+    let option = server.tmux(&["show-options", "-v", "-t", "tools", "default-command"]);
+    assert_eq!(
+        String::from_utf8_lossy(&option.stdout).trim(),
+        "direnv exec . $SHELL"
+    );
+
+    assert_success(&server.mukduk(&[
+        "-c", config, "project", "open", "-m", "tmux", "-d", "cloned",
+    ]));
+    let option = server.tmux(&["show-options", "-v", "-t", "cloned", "default-command"]);
+    let option = String::from_utf8_lossy(&option.stdout);
+    assert!(!option.contains("direnv"), "{option}");
+}
+
+#[test]
+fn create_scaffolds_template_and_opens_session() {
+    let Some(server) = TmuxServer::start() else {
+        return;
+    };
+    let template = server.dir.path().join("templates").join("rust");
+    fs::create_dir_all(&template).expect("template should be created");
+    fs::write(template.join("README.md"), "# {{name}}\n").expect("file should be written");
+    let config = server.dir.path().join("config.toml");
+    fs::write(
+        &config,
+        format!(
+            "[templates]\nrust = {:?}\n",
+            template.to_str().expect("utf8 path")
+        ),
+    )
+    .expect("config should be written");
+
+    assert_success(&server.mukduk(&[
+        "-c",
+        config.to_str().expect("utf8 path"),
+        "project",
+        "create",
+        "demo",
+        "--template",
+        "rust",
+        "-m",
+        "tmux",
+        "-d",
+    ]));
+
+    let project = server.dir.path().join("projects").join("demo");
+    assert_eq!(
+        fs::read_to_string(project.join("README.md")).expect("README should exist"),
+        "# demo\n"
+    );
+    assert!(project.join(".git").is_dir());
+    let sessions = server.sessions();
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].0, "demo");
+    assert_eq!(canonical(&sessions[0].1), canonical(&project));
+}
+
+#[test]
+fn session_list_prints_template_per_session() {
+    let Some(server) = TmuxServer::start() else {
+        return;
+    };
+    assert_success(&server.mukduk(&["project", "scratch", "-m", "tmux", "-d"]));
+    assert_success(&server.mukduk(&["project", "scratch", "-m", "tmux", "-d", "-n", "other"]));
+
+    let output = server.mukduk(&[
+        "session",
+        "list",
+        "-m",
+        "tmux",
+        "--template",
+        "{multiplexer}:{name}",
+    ]);
+    assert_success(&output);
+
+    let mut listed: Vec<_> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::to_string)
+        .collect();
+    listed.sort();
+    assert_eq!(listed, vec!["tmux:other", "tmux:scratch"]);
+}
+
+#[test]
+fn switch_last_fails_before_any_switch() {
+    let Some(server) = TmuxServer::start() else {
+        return;
+    };
+    let output = server.mukduk(&["switch", "--last"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("has not switched sessions yet"));
+}
+
+#[test]
+fn failed_open_is_not_remembered_for_switch_last() {
+    let Some(server) = TmuxServer::start() else {
+        return;
+    };
+    let project = server.project("mukduk");
+
+    // Attaching needs a terminal, which the tests do not have.
+    let output = server.mukduk(&[
+        "project",
+        "open",
+        "-m",
+        "tmux",
+        "-p",
+        project.to_str().expect("utf8 path"),
+    ]);
+    assert!(!output.status.success());
+
+    let output = server.mukduk(&["switch", "--last"]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("has not switched sessions yet"));
+}
+
+#[test]
+fn tmux_socket_flag_accepts_socket_path() {
+    let Some(server) = TmuxServer::start() else {
+        return;
+    };
+    let socket = server.dir.path().join("pair.sock");
+    let socket = socket.to_str().expect("utf8 path");
+
+    let mut command = Command::new(env!("CARGO_BIN_EXE_mukduk"));
+    server.env(&mut command);
+    let output = command
+        .args(["--tmux-socket", socket])
+        .args(["project", "scratch", "-m", "tmux", "-d", "-n", "pair"])
+        .output()
+        .expect("mukduk should run");
+    assert_success(&output);
+
+    let listed = Command::new("tmux")
+        .args(["-S", socket, "ls", "-F", "#{session_name}"])
+        .output()
+        .expect("tmux should run");
+    Command::new("tmux")
+        .args(["-S", socket, "kill-server"])
+        .output()
+        .expect("tmux should run");
+
+    assert_eq!(String::from_utf8_lossy(&listed.stdout).trim(), "pair");
+    assert_eq!(server.session_names(), Vec::<String>::new());
+}